@@ -0,0 +1,211 @@
+use log::error;
+use rquickjs::{object::ObjectKeysIter, Array, Context, Ctx, Persistent, Value};
+use wasm_macros::wasm_export;
+
+/// Maximum nesting depth `encode_value`/`decode_value` will walk, guarding against
+/// cyclic or pathologically deep structures. Exceeding it does not fail an encode -
+/// the offending subtree is replaced with `TAG_DEPTH_OVERFLOW`, which decodes back as
+/// null. Decoding fails on input whose arrays/objects nest deeper than this, which
+/// `array_to_bytes` never produces.
+const MAX_DEPTH: u32 = 256;
+
+const TAG_NULL: u8 = 0x00;
+const TAG_BOOL: u8 = 0x01;
+const TAG_F64: u8 = 0x02;
+const TAG_I32: u8 = 0x03;
+const TAG_STRING: u8 = 0x04;
+const TAG_ARRAY: u8 = 0x05;
+const TAG_OBJECT: u8 = 0x06;
+/// Emitted in place of a value whose depth exceeded `MAX_DEPTH`, so a cyclic or
+/// overly deep structure truncates cleanly instead of overflowing the stack.
+const TAG_DEPTH_OVERFLOW: u8 = 0xFF;
+
+/// Serializes the elements of `array` into a compact, self-describing binary form -
+/// one tag byte per value followed by its payload - so Java can ship or receive an
+/// entire array in a single `alloc`/`dealloc` round trip instead of one
+/// `array_get`/`array_set` call per element.
+#[wasm_export]
+pub fn array_to_bytes(context: &Context, persistent_array: &Persistent<Array<'static>>) -> Vec<u8> {
+    context.with(|ctx| match persistent_array.clone().restore(&ctx) {
+        Ok(array) => {
+            let mut bytes = Vec::new();
+            match encode_value(&ctx, &array.into_value(), &mut bytes, 0) {
+                Ok(()) => bytes,
+                Err(err) => {
+                    error!("Failed to encode array to bytes: {}", err);
+                    Vec::new()
+                }
+            }
+        }
+        Err(err) => {
+            error!("Failed to restore persistent array: {}", err);
+            Vec::new()
+        }
+    })
+}
+
+/// Reverses `array_to_bytes`, rebuilding a fresh `rquickjs::Array` from the tagged
+/// binary form. Returns `None` (logging the cause) if `bytes` is malformed or the
+/// top-level value it encodes is not an array.
+#[wasm_export]
+pub fn array_from_bytes(context: &Context, bytes: Vec<u8>) -> Option<Box<Persistent<Array<'static>>>> {
+    context.with(|ctx| {
+        let mut cursor = 0usize;
+        let value = match decode_value(&ctx, &bytes, &mut cursor, 0) {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to decode array from bytes: {}", err);
+                return None;
+            }
+        };
+        match value.into_array() {
+            Some(array) => Some(Box::new(Persistent::save(&ctx, array))),
+            None => {
+                error!("Decoded bytes did not encode an array at the top level");
+                None
+            }
+        }
+    })
+}
+
+fn encode_value<'js>(
+    ctx: &Ctx<'js>,
+    value: &Value<'js>,
+    out: &mut Vec<u8>,
+    depth: u32,
+) -> rquickjs::Result<()> {
+    // Only arrays/objects can recurse further, so the depth guard only needs to
+    // apply to them - a leaf (string, number, ...) can never overflow. This also
+    // keeps an object's keys (always plain strings, encoded below without going
+    // through this check) from ever being replaced by `TAG_DEPTH_OVERFLOW`, which
+    // `decode_value` cannot interpret as a key.
+    if (value.is_array() || (value.is_object() && !value.is_function())) && depth > MAX_DEPTH {
+        out.push(TAG_DEPTH_OVERFLOW);
+        return Ok(());
+    }
+
+    if value.is_array() {
+        let array = value.as_array().unwrap();
+        out.push(TAG_ARRAY);
+        out.extend_from_slice(&(array.len() as u32).to_le_bytes());
+        for i in 0..array.len() {
+            let element: Value = array.get(i)?;
+            encode_value(ctx, &element, out, depth + 1)?;
+        }
+    } else if value.is_object() && !value.is_function() {
+        let object = value.as_object().unwrap();
+        let keys: ObjectKeysIter<'_, String> = object.keys();
+        let keys = keys.collect::<rquickjs::Result<Vec<_>>>()?;
+        out.push(TAG_OBJECT);
+        out.extend_from_slice(&(keys.len() as u32).to_le_bytes());
+        for key in keys {
+            encode_string(&key, out);
+            let value: Value = object.get(&key)?;
+            encode_value(ctx, &value, out, depth + 1)?;
+        }
+    } else if value.is_string() {
+        let s = value.as_string().unwrap().to_string()?;
+        encode_string(&s, out);
+    } else if value.is_int() {
+        out.push(TAG_I32);
+        out.extend_from_slice(&value.as_int().unwrap().to_le_bytes());
+    } else if value.is_float() {
+        out.push(TAG_F64);
+        out.extend_from_slice(&value.as_float().unwrap().to_le_bytes());
+    } else if value.is_bool() {
+        out.push(TAG_BOOL);
+        out.push(value.as_bool().unwrap() as u8);
+    } else {
+        // null/undefined and any other non-cloneable leaf (function, symbol, ...).
+        out.push(TAG_NULL);
+    }
+
+    Ok(())
+}
+
+/// Encodes a plain Rust string as `TAG_STRING`, for object keys - which are never
+/// subject to the depth-overflow guard, since a key is always a leaf, not a
+/// recursive container.
+fn encode_string(s: &str, out: &mut Vec<u8>) {
+    out.push(TAG_STRING);
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn decode_value<'js>(
+    ctx: &Ctx<'js>,
+    bytes: &[u8],
+    cursor: &mut usize,
+    depth: u32,
+) -> rquickjs::Result<Value<'js>> {
+    let tag = read_u8(bytes, cursor)?;
+    // Only arrays/objects can nest further; the `TAG_DEPTH_OVERFLOW` leaf that
+    // `encode_value` writes one level past `MAX_DEPTH` must still decode (as null).
+    if (tag == TAG_ARRAY || tag == TAG_OBJECT) && depth > MAX_DEPTH {
+        return Err(rquickjs::Error::Unknown);
+    }
+    match tag {
+        TAG_NULL => Ok(Value::new_null(ctx.clone())),
+        TAG_BOOL => {
+            let b = read_u8(bytes, cursor)? != 0;
+            Ok(Value::new_bool(ctx.clone(), b))
+        }
+        TAG_F64 => {
+            let f = f64::from_le_bytes(read_n::<8>(bytes, cursor)?);
+            Ok(Value::new_float(ctx.clone(), f))
+        }
+        TAG_I32 => {
+            let i = i32::from_le_bytes(read_n::<4>(bytes, cursor)?);
+            Ok(Value::new_int(ctx.clone(), i))
+        }
+        TAG_STRING => {
+            let len = u32::from_le_bytes(read_n::<4>(bytes, cursor)?) as usize;
+            let slice = read_slice(bytes, cursor, len)?;
+            let s = String::from_utf8(slice.to_vec()).map_err(|_| rquickjs::Error::Unknown)?;
+            Ok(rquickjs::String::from_str(ctx.clone(), &s)?.into_value())
+        }
+        TAG_ARRAY => {
+            let count = u32::from_le_bytes(read_n::<4>(bytes, cursor)?) as usize;
+            let array = Array::new(ctx.clone())?;
+            for i in 0..count {
+                let element = decode_value(ctx, bytes, cursor, depth + 1)?;
+                array.set(i, element)?;
+            }
+            Ok(array.into_value())
+        }
+        TAG_OBJECT => {
+            let count = u32::from_le_bytes(read_n::<4>(bytes, cursor)?) as usize;
+            let object = rquickjs::Object::new(ctx.clone())?;
+            for _ in 0..count {
+                let key = decode_value(ctx, bytes, cursor, depth + 1)?;
+                let value = decode_value(ctx, bytes, cursor, depth + 1)?;
+                let key = key
+                    .as_string()
+                    .ok_or(rquickjs::Error::Unknown)?
+                    .to_string()?;
+                object.set(key, value)?;
+            }
+            Ok(object.into_value())
+        }
+        TAG_DEPTH_OVERFLOW => Ok(Value::new_null(ctx.clone())),
+        _ => Err(rquickjs::Error::Unknown),
+    }
+}
+
+fn read_u8(bytes: &[u8], cursor: &mut usize) -> rquickjs::Result<u8> {
+    let byte = *bytes.get(*cursor).ok_or(rquickjs::Error::Unknown)?;
+    *cursor += 1;
+    Ok(byte)
+}
+
+fn read_n<const N: usize>(bytes: &[u8], cursor: &mut usize) -> rquickjs::Result<[u8; N]> {
+    let slice = read_slice(bytes, cursor, N)?;
+    slice.try_into().map_err(|_| rquickjs::Error::Unknown)
+}
+
+fn read_slice<'a>(bytes: &'a [u8], cursor: &mut usize, len: usize) -> rquickjs::Result<&'a [u8]> {
+    let end = cursor.checked_add(len).ok_or(rquickjs::Error::Unknown)?;
+    let slice = bytes.get(*cursor..end).ok_or(rquickjs::Error::Unknown)?;
+    *cursor = end;
+    Ok(slice)
+}
@@ -0,0 +1,147 @@
+use log::{debug, error};
+use rquickjs::{ArrayBuffer, Context, Object, Persistent, TypedArray, Value};
+use wasm_macros::wasm_export;
+
+/// Creates a JS `ArrayBuffer` directly over a host-owned byte region: `ptr`/`len`
+/// describe memory the host previously obtained from `alloc(size)`. `ArrayBuffer::new`
+/// takes the `Vec<u8>` we rebuild here by value, forgets it, and registers QuickJS's
+/// own drop callback against its buffer pointer - so the bytes become the buffer's
+/// backing store with no copy, and `arraybuffer_close`/GC eventually frees them
+/// instead of the host calling `dealloc` on them itself.
+#[wasm_export]
+pub fn arraybuffer_create(
+    context: &Context,
+    ptr: u64,
+    len: u64,
+) -> Box<Persistent<ArrayBuffer<'static>>> {
+    let bytes: Vec<u8> = unsafe { Vec::from_raw_parts(ptr as *mut u8, len as usize, len as usize) };
+    let result = context.with(|ctx| {
+        let buffer = ArrayBuffer::new(ctx.clone(), bytes).unwrap();
+        Persistent::save(&ctx, buffer)
+    });
+    Box::new(result)
+}
+
+#[wasm_export]
+pub fn arraybuffer_close(_context: &Context, object: Box<Persistent<ArrayBuffer<'static>>>) -> bool {
+    drop(object);
+    true
+}
+
+#[wasm_export]
+pub fn arraybuffer_size(
+    context: &Context,
+    persistent_buffer: &Persistent<ArrayBuffer<'static>>,
+) -> i32 {
+    let result = context.with(|ctx| match persistent_buffer.clone().restore(&ctx) {
+        Ok(buf) => buf.len() as i32,
+        Err(err) => {
+            error!("Failed to restore persistent ArrayBuffer: {}", err);
+            -1
+        }
+    });
+    debug!("Size of the native ArrayBuffer {}", result);
+    result
+}
+
+/// Copies the buffer's bytes out for the host to read, packed as a ptr+len pair the
+/// host reclaims via `dealloc` - unlike `arraybuffer_create`, this direction always
+/// copies, since the buffer's backing store stays owned by QuickJS.
+#[wasm_export]
+pub fn arraybuffer_read(
+    context: &Context,
+    persistent_buffer: &Persistent<ArrayBuffer<'static>>,
+) -> Vec<u8> {
+    context.with(|ctx| match persistent_buffer.clone().restore(&ctx) {
+        Ok(buf) => buf.as_bytes().map(|bytes| bytes.to_vec()).unwrap_or_default(),
+        Err(err) => {
+            error!("Failed to restore persistent ArrayBuffer: {}", err);
+            Vec::new()
+        }
+    })
+}
+
+/// Overwrites the buffer's backing bytes in place with `bytes`. The buffer cannot be
+/// resized, so the lengths must match exactly.
+#[wasm_export]
+pub fn arraybuffer_write(
+    context: &Context,
+    persistent_buffer: &Persistent<ArrayBuffer<'static>>,
+    bytes: Vec<u8>,
+) -> bool {
+    context.with(|ctx| match persistent_buffer.clone().restore(&ctx) {
+        Ok(buf) => match buf.as_bytes() {
+            Some(existing) if existing.len() == bytes.len() => {
+                // SAFETY: `existing` aliases the buffer's backing store; writing
+                // exactly `existing.len()` bytes back into it cannot change its size
+                // or layout. No JS code runs between the length check and the copy.
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        bytes.as_ptr(),
+                        existing.as_ptr() as *mut u8,
+                        bytes.len(),
+                    );
+                }
+                true
+            }
+            Some(existing) => {
+                error!(
+                    "arraybuffer_write length mismatch: buffer is {} bytes, got {}",
+                    existing.len(),
+                    bytes.len()
+                );
+                false
+            }
+            None => {
+                error!("Cannot write to a detached ArrayBuffer");
+                false
+            }
+        },
+        Err(err) => {
+            error!("Failed to restore persistent ArrayBuffer: {}", err);
+            false
+        }
+    })
+}
+
+/// Stamps out a `<kind>array_from_buffer` export wrapping a persisted `ArrayBuffer`
+/// in the matching `TypedArray` view class, so JS code sees a proper `Uint8Array`/
+/// `Int32Array`/etc. instead of having to wrap the buffer itself every time.
+macro_rules! typed_array_view {
+    ($name:ident, $elem:ty) => {
+        #[wasm_export]
+        pub fn $name(
+            context: &Context,
+            persistent_buffer: &Persistent<ArrayBuffer<'static>>,
+        ) -> Option<Box<Persistent<Object<'static>>>> {
+            context.with(|ctx| {
+                let buffer = match persistent_buffer.clone().restore(&ctx) {
+                    Ok(buffer) => buffer,
+                    Err(err) => {
+                        error!("Failed to restore persistent ArrayBuffer: {}", err);
+                        return None;
+                    }
+                };
+                match TypedArray::<$elem>::from_arraybuffer(ctx.clone(), buffer) {
+                    Ok(view) => {
+                        let object = Value::from(view).into_object().unwrap();
+                        Some(Box::new(Persistent::save(&ctx, object)))
+                    }
+                    Err(err) => {
+                        error!("Failed to create {} view: {}", stringify!($name), err);
+                        None
+                    }
+                }
+            })
+        }
+    };
+}
+
+typed_array_view!(uint8array_from_buffer, u8);
+typed_array_view!(int8array_from_buffer, i8);
+typed_array_view!(uint16array_from_buffer, u16);
+typed_array_view!(int16array_from_buffer, i16);
+typed_array_view!(uint32array_from_buffer, u32);
+typed_array_view!(int32array_from_buffer, i32);
+typed_array_view!(float32array_from_buffer, f32);
+typed_array_view!(float64array_from_buffer, f64);
@@ -0,0 +1,144 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use log::error;
+use rquickjs::function::{IntoJsFunc, ParamRequirement, Params};
+use rquickjs::{Ctx, JsLifetime, Value};
+use wasm_macros::wasm_export;
+
+use crate::completable_future::PromiseContainer;
+use crate::js_to_java_proxy::JSJavaProxy;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    /// Like `call_java_function`, but tells Java the call does not have to complete
+    /// synchronously: instead of a result, Java immediately returns a token
+    /// identifying the call, and settles it whenever it is ready by calling back into
+    /// `resolve_promise` with that same token.
+    pub fn call_java_function_async(
+        context: i32,
+        function: i32,
+        args_ptr: *const u8,
+        args_len: usize,
+    ) -> i64;
+}
+
+/// Pending non-blocking `JavaFunction` calls, keyed by the token Java handed back
+/// from `call_java_function_async`. Stored as `Ctx` userdata, one table per
+/// `Context`. Unlike `HandleRegistry`'s generational slots, the key space here is
+/// minted by Java, not us - we only ever insert a freshly minted token or remove one
+/// on settlement.
+#[derive(Default)]
+pub struct PendingCallRegistry {
+    pending: RefCell<HashMap<u64, PromiseContainer>>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for PendingCallRegistry {
+    type Changed<'to> = PendingCallRegistry;
+}
+
+impl PendingCallRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, token: u64, container: PromiseContainer) {
+        self.pending.borrow_mut().insert(token, container);
+    }
+
+    /// Removes and returns the container for `token`, so a given call is settled (and
+    /// its `Persistent` resolve/reject handles freed) at most once.
+    pub fn take(&self, token: u64) -> Option<PromiseContainer> {
+        self.pending.borrow_mut().remove(&token)
+    }
+}
+
+/// Non-blocking counterpart to `JavaFunction`: instead of blocking the calling
+/// script on `call_java_function`, immediately returns a `Promise` and lets
+/// `resolve_promise` settle it once Java is ready.
+pub struct AsyncJavaCall {
+    context: i32,
+    func: i32,
+}
+
+impl AsyncJavaCall {
+    pub fn new(context: i32, func: i32) -> Self {
+        Self { context, func }
+    }
+}
+
+impl<'js, P> IntoJsFunc<'js, P> for AsyncJavaCall {
+    fn param_requirements() -> ParamRequirement {
+        // We cannot give any hint on the number of expected parameters
+        ParamRequirement::any()
+    }
+
+    fn call<'a>(&self, params: Params<'a, 'js>) -> rquickjs::Result<Value<'js>> {
+        let ctx = params.ctx();
+
+        let mut args: Vec<JSJavaProxy> = Vec::new();
+        for i in 0..params.len() {
+            if let Some(v) = params.arg(i) {
+                args.push(JSJavaProxy::convert(v)?);
+            }
+        }
+        let arg = JSJavaProxy::Array(args);
+        let bytes = rmp_serde::to_vec(&arg).expect("MsgPack encode failed");
+        let args_len = bytes.len();
+        let args_ptr = bytes.as_ptr();
+        std::mem::forget(bytes); // Prevent drop, Java reclaims via dealloc
+
+        let (promise, resolve, reject) = ctx.promise()?;
+
+        let token =
+            unsafe { call_java_function_async(self.context, self.func, args_ptr, args_len) }
+                as u64;
+
+        let container = PromiseContainer::new(ctx, promise.clone(), Some(resolve), Some(reject));
+        let registry = ctx
+            .userdata::<PendingCallRegistry>()
+            .ok_or(rquickjs::Error::Unknown)?;
+        registry.insert(token, container);
+
+        Ok(promise.into_value())
+    }
+}
+
+/// Settles the non-blocking call identified by `token` (as handed back by
+/// `call_java_function_async`) with `value`, then drops the pending entry so its
+/// resolve/reject handles are freed. `is_error` rejects the promise instead of
+/// resolving it; a `JSJavaProxy::Exception` value then surfaces to the calling
+/// script as the matching typed JS error, exactly like a synchronous Java exception
+/// already does through `JavaFunction::invoke`. Returns `false` for an unknown or
+/// already-settled token instead of erroring, since a late or duplicate settlement
+/// from Java is a harmless no-op rather than a bug worth propagating.
+#[wasm_export]
+pub fn resolve_promise(
+    ctx: &Ctx<'_>,
+    token: u64,
+    value: JSJavaProxy,
+    is_error: bool,
+) -> rquickjs::Result<bool> {
+    let registry = ctx
+        .userdata::<PendingCallRegistry>()
+        .ok_or(rquickjs::Error::Unknown)?;
+
+    let container = match registry.take(token) {
+        Some(container) => container,
+        None => {
+            error!(
+                "resolve_promise called with unknown or already-settled token {}",
+                token
+            );
+            return Ok(false);
+        }
+    };
+
+    if is_error {
+        container.reject(ctx, value)?;
+    } else {
+        container.resolve(ctx, value)?;
+    }
+
+    Ok(true)
+}
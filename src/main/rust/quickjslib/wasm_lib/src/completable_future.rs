@@ -4,11 +4,15 @@ use rquickjs::{
     prelude::This,
     promise::PromiseState,
     runtime::UserDataGuard,
-    Context, Ctx, Function, IntoJs, Persistent, Promise, Value,
+    Array, Context, Ctx, Function, IntoJs, Object, Persistent, Promise, Value,
 };
 use wasm_macros::wasm_export;
 
-use crate::{context::ContextPtr, js_to_java_proxy::JSJavaProxy};
+use crate::{
+    context::{handle_error, with_context, ContextPtr},
+    handle_registry::RegisteredHandle,
+    js_to_java_proxy::{with_registered_handle, JSJavaProxy},
+};
 
 /**
  * In this private field in any promise, we store a reference to the corresponding java completable future
@@ -176,7 +180,7 @@ impl<'js, P> IntoJsFunc<'js, P> for JavaPromise {
                     );
                     match promise.finish::<Value>() {
                         Ok(v) => JSJavaProxy::convert(v)?,
-                        Err(e) => JSJavaProxy::Exception(format!("{:?}", e), "".to_string()),
+                        Err(e) => JSJavaProxy::error(format!("{:?}", e), ""),
                     }
                 }
                 PromiseState::Pending => JSJavaProxy::Null,
@@ -231,7 +235,12 @@ pub(crate) fn convert_promise<'js>(promise: Promise<'js>) -> rquickjs::Result<JS
         ptr
     } else {
         let promise_container = PromiseContainer::new(&ctx, promise.clone(), None, None);
-        let promise_ptr = Box::into_raw(Box::new(promise_container)) as u64;
+        let registry = ctx
+            .userdata::<crate::handle_registry::HandleRegistry>()
+            .ok_or(rquickjs::Error::Unknown)?;
+        let promise_ptr = registry.register(crate::handle_registry::RegisteredHandle::Promise(
+            Box::new(promise_container),
+        ));
 
         promise.set(JS_PROMISE_CONTAINER_PTR_FIELD, promise_ptr)?;
         debug!(
@@ -304,3 +313,156 @@ pub(crate) fn convert_promise<'js>(promise: Promise<'js>) -> rquickjs::Result<JS
         promise_ptr,
     ));
 }
+
+/// Settles `promise` synchronously by draining the pending-job queue until it leaves
+/// `Pending`, then returns the settled value directly instead of routing through a
+/// `CompletableFuture`. Useful for synchronous Java call sites.
+///
+/// If `max_iterations` is exhausted, or the job queue runs dry, while the promise is
+/// still `Pending` (an unresolvable await), a timeout `JSJavaProxy::Exception` is
+/// returned instead of looping forever.
+#[wasm_export]
+pub fn promise_await(
+    context: &Context,
+    promise: &Persistent<Promise<'static>>,
+    max_iterations: i32,
+) -> JSJavaProxy {
+    with_context(context, |ctx| {
+        let restored = match promise.clone().restore(&ctx) {
+            Ok(p) => p,
+            Err(err) => return handle_error(err, ctx),
+        };
+
+        let mut iterations = 0;
+        loop {
+            match restored.state() {
+                PromiseState::Pending => {
+                    if iterations >= max_iterations {
+                        debug!(
+                            "promise_await exhausted {} iterations while still pending",
+                            max_iterations
+                        );
+                        return JSJavaProxy::error(
+                            "Timeout",
+                            format!(
+                                "promise_await exhausted {} iterations without the promise settling",
+                                max_iterations
+                            ),
+                        );
+                    }
+                    if !ctx.execute_pending_job() {
+                        return JSJavaProxy::error(
+                            "Timeout",
+                            "promise_await: no pending jobs left but the promise never settled",
+                        );
+                    }
+                    iterations += 1;
+                }
+                PromiseState::Resolved => {
+                    let value = restored.finish::<Value>().unwrap();
+                    return JavaPromise::convert_value(value).unwrap_or(JSJavaProxy::Undefined);
+                }
+                PromiseState::Rejected => {
+                    return match restored.finish::<Value>() {
+                        Ok(v) => JSJavaProxy::convert(v).unwrap_or(JSJavaProxy::Undefined),
+                        Err(e) => JSJavaProxy::error(format!("{:?}", e), ""),
+                    };
+                }
+            }
+        }
+    })
+}
+
+/// Inspects `promise`'s current state cheaply, without creating a `CompletableFuture`.
+/// Returns a `[state, value]` pair: state tag `0` (Pending), `1` (Fulfilled) or `2`
+/// (Rejected), with `value` the settled value when not pending (`Null` otherwise).
+/// Lets callers build their own coordination, e.g. busy-polling combined with
+/// `context_run_pending_jobs`.
+#[wasm_export]
+pub fn promise_state(context: &Context, promise: &Persistent<Promise<'static>>) -> JSJavaProxy {
+    with_context(context, |ctx| {
+        let restored = match promise.clone().restore(&ctx) {
+            Ok(p) => p,
+            Err(err) => return handle_error(err, ctx),
+        };
+
+        let (tag, value) = match restored.state() {
+            PromiseState::Pending => (0, JSJavaProxy::Null),
+            PromiseState::Resolved => {
+                let value = restored.finish::<Value>().unwrap();
+                (
+                    1,
+                    JavaPromise::convert_value(value).unwrap_or(JSJavaProxy::Undefined),
+                )
+            }
+            PromiseState::Rejected => {
+                let value = match restored.finish::<Value>() {
+                    Ok(v) => JSJavaProxy::convert(v).unwrap_or(JSJavaProxy::Undefined),
+                    Err(e) => JSJavaProxy::error(format!("{:?}", e), ""),
+                };
+                (2, value)
+            }
+        };
+
+        JSJavaProxy::Array(vec![JSJavaProxy::Int(tag), value])
+    })
+}
+
+/// Restores the `HandleRegistry` handles in `ptrs` (as registered by `convert_promise`)
+/// into a fresh JS array, in order, for handing to a `Promise` combinator.
+fn restore_promises<'js>(ctx: &Ctx<'js>, ptrs: &[u64]) -> rquickjs::Result<Array<'js>> {
+    let array = Array::new(ctx.clone())?;
+    for (i, handle) in ptrs.iter().enumerate() {
+        let promise = with_registered_handle(ctx, *handle, |h| match h {
+            RegisteredHandle::Promise(container) => container.promise.clone().restore(ctx),
+            _ => Err(rquickjs::Error::Unknown),
+        })?;
+        array.set(i, promise)?;
+    }
+    Ok(array)
+}
+
+/// Builds the aggregate promise for the named `Promise` combinator (`all`, `race`,
+/// `allSettled`, `any`) over `promises`, then funnels it through `convert_promise` so
+/// Java gets back a single `CompletableFuture` instead of re-implementing fan-in/out.
+fn promise_combinator(
+    context: &Context,
+    promises: Vec<u64>,
+    method: &str,
+) -> rquickjs::Result<JSJavaProxy> {
+    with_context(context, |ctx| {
+        let array = restore_promises(&ctx, &promises)?;
+        let promise_ctor: Object = ctx.globals().get("Promise")?;
+        let combinator: Function = promise_ctor.get(method)?;
+        let aggregate: Promise = combinator.call((This(promise_ctor.clone()), array))?;
+        convert_promise(aggregate)
+    })
+}
+
+/// Resolves once every promise in `promises` has fulfilled, or rejects as soon as one
+/// rejects (mirrors `Promise.all`).
+#[wasm_export]
+pub fn promise_all(context: &Context, promises: Vec<u64>) -> rquickjs::Result<JSJavaProxy> {
+    promise_combinator(context, promises, "all")
+}
+
+/// Settles as soon as the first of `promises` settles, fulfilled or rejected (mirrors
+/// `Promise.race`).
+#[wasm_export]
+pub fn promise_race(context: &Context, promises: Vec<u64>) -> rquickjs::Result<JSJavaProxy> {
+    promise_combinator(context, promises, "race")
+}
+
+/// Resolves once every promise in `promises` has settled, never rejecting (mirrors
+/// `Promise.allSettled`).
+#[wasm_export]
+pub fn promise_all_settled(context: &Context, promises: Vec<u64>) -> rquickjs::Result<JSJavaProxy> {
+    promise_combinator(context, promises, "allSettled")
+}
+
+/// Resolves as soon as the first of `promises` fulfills, or rejects with an
+/// `AggregateError` if all of them reject (mirrors `Promise.any`).
+#[wasm_export]
+pub fn promise_any(context: &Context, promises: Vec<u64>) -> rquickjs::Result<JSJavaProxy> {
+    promise_combinator(context, promises, "any")
+}
@@ -1,4 +1,5 @@
 use std::cell::RefCell;
+use std::collections::HashSet;
 
 use log::debug;
 use log::error;
@@ -6,9 +7,12 @@ use rquickjs::Context;
 use rquickjs::Ctx;
 use rquickjs::FromJs;
 use rquickjs::JsLifetime;
+use rquickjs::Promise;
 use rquickjs::Runtime;
+use rquickjs::Value;
 use wasm_macros::wasm_export;
 
+use crate::from_error::FromError;
 use crate::js_to_java_proxy::JSJavaProxy;
 
 pub struct ContextPtr {
@@ -25,11 +29,105 @@ unsafe impl<'js> JsLifetime<'js> for ContextPtr {
     type Changed<'to> = ContextPtr;
 }
 
+/// Private field used to attach a stable identity to a promise so the rejection
+/// tracker can recognize it again across the `Reject`/`Handle` notifications.
+pub static PROMISE_REJECTION_ID_FIELD: &str = "___promise_rejection_id";
+
+thread_local! {
+    /// Raw promise identities currently in the "rejected with no handler attached" state.
+    static UNHANDLED_REJECTIONS: RefCell<HashSet<u64>> = RefCell::new(HashSet::new());
+
+    /// Source of the ids `promise_identity` hands out - a plain counter rather than a
+    /// pointer, since nothing ever needs to dereference a promise back out of one; the
+    /// id only has to be unique and stable for the life of the promise.
+    static NEXT_PROMISE_ID: RefCell<u64> = RefCell::new(1);
+}
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    /// Notifies the Java side about a promise rejection tracker event.
+    /// `handled` is 1 if the promise gained a handler, 0 if it just became rejected with none.
+    pub fn report_promise_rejection(
+        context_ptr: u64,
+        promise_ptr: u64,
+        handled: i32,
+        reason_ptr: *const u8,
+        reason_len: usize,
+    );
+}
+
+/// Returns a stable identity for `promise`, attaching one on first use. The id is
+/// only ever used as an opaque key (the unhandled-rejection set, the integer reported
+/// to Java) - nothing restores a promise from it - so it's a plain counter rather than
+/// a boxed `Persistent`, which would have to be freed by someone and never was.
+fn promise_identity<'js>(promise: &Promise<'js>) -> rquickjs::Result<u64> {
+    if let Ok(id) = promise.get::<_, u64>(PROMISE_REJECTION_ID_FIELD) {
+        return Ok(id);
+    }
+
+    let id = NEXT_PROMISE_ID.with(|next| {
+        let mut next = next.borrow_mut();
+        let id = *next;
+        *next += 1;
+        id
+    });
+    promise.set(PROMISE_REJECTION_ID_FIELD, id)?;
+    Ok(id)
+}
+
+/// Registers the `HostPromiseRejectionTracker` hook on `runtime`, mirroring the
+/// ECMAScript hook: `Reject` marks a promise as rejected-unhandled, `Handle` clears it.
+///
+/// The tracker fires before the microtask that would attach a `.catch`, so the
+/// `Reject`/`Handle` notifications are forwarded to Java as-is rather than being
+/// resolved here; the Java side reconciles them once the job queue drains.
+fn install_promise_rejection_tracker(context_ptr: u64, runtime: &Runtime) {
+    runtime.set_host_promise_rejection_tracker(Some(Box::new(
+        move |_ctx: Ctx, promise: Promise, reason: Value, is_handled: bool| {
+            let promise_ptr = match promise_identity(&promise) {
+                Ok(id) => id,
+                Err(err) => {
+                    error!("Failed to compute identity for promise in rejection tracker: {}", err);
+                    return;
+                }
+            };
+
+            if is_handled {
+                UNHANDLED_REJECTIONS.with(|set| set.borrow_mut().remove(&promise_ptr));
+                unsafe {
+                    report_promise_rejection(context_ptr, promise_ptr, 1, std::ptr::null(), 0);
+                }
+            } else {
+                UNHANDLED_REJECTIONS.with(|set| set.borrow_mut().insert(promise_ptr));
+
+                let reason = JSJavaProxy::convert(reason).unwrap_or(JSJavaProxy::Undefined);
+                let bytes = rmp_serde::to_vec(&reason).expect("MsgPack encode failed");
+                let len = bytes.len();
+                let ptr = bytes.as_ptr();
+                std::mem::forget(bytes); // Prevent drop, Java reclaims via dealloc
+
+                unsafe {
+                    report_promise_rejection(context_ptr, promise_ptr, 0, ptr, len);
+                }
+            }
+        },
+    )));
+}
+
 #[wasm_export]
 pub fn create_context(runtime: &Runtime) -> Box<Context> {
-    let context = Context::full(runtime).unwrap();
+    let context = Box::new(Context::full(runtime).unwrap());
+    let context_ptr = context.as_ref() as *const Context as u64;
+    install_promise_rejection_tracker(context_ptr, runtime);
+    context.with(|ctx| {
+        let _ = ctx.store_userdata(ContextPtr::new(context_ptr));
+        let _ = ctx.store_userdata(crate::handle_registry::HandleRegistry::new());
+        let _ = ctx.store_userdata(crate::async_java_function::PendingCallRegistry::new());
+        let _ = ctx.store_userdata(crate::source_map::SourceMapRegistry::new());
+        let _ = ctx.store_userdata(crate::last_error::LastErrorSlot::new());
+    });
     debug!("Created new QuickJS context");
-    Box::new(context)
+    context
 }
 
 #[wasm_export]
@@ -58,6 +156,130 @@ pub fn poll(ctx: &Ctx<'_>) -> rquickjs::Result<bool> {
     Ok(ctx.execute_pending_job())
 }
 
+/// Compiles `script` without executing it and serializes the resulting function
+/// object to a portable QuickJS bytecode blob, so Java can cache it (keyed by the
+/// owning `Runtime`) and skip re-parsing the source text on every `eval_bytecode`.
+///
+/// The blob is only valid for the exact QuickJS build that wrote it - there is no
+/// cross-version compatibility check beyond what `JS_ReadObject` itself rejects.
+#[wasm_export]
+pub fn compile_script(ctx: &Ctx<'_>, script: String) -> rquickjs::Result<Vec<u8>> {
+    debug!("Compiling script to bytecode ({} source bytes)", script.len());
+
+    let c_script = std::ffi::CString::new(script.as_str()).map_err(|_| rquickjs::Error::Unknown)?;
+    let c_filename = std::ffi::CString::new("<compile_script>").unwrap();
+
+    unsafe {
+        let raw_ctx = ctx.as_raw().as_ptr();
+
+        let compiled = rquickjs::qjs::JS_Eval(
+            raw_ctx,
+            c_script.as_ptr(),
+            script.len() as _,
+            c_filename.as_ptr(),
+            (rquickjs::qjs::JS_EVAL_TYPE_GLOBAL | rquickjs::qjs::JS_EVAL_FLAG_COMPILE_ONLY) as i32,
+        );
+
+        if rquickjs::qjs::JS_IsException(compiled) != 0 {
+            rquickjs::qjs::JS_FreeValue(raw_ctx, compiled);
+            error!("Failed to compile script to bytecode (syntax error)");
+            return Err(rquickjs::Error::Exception);
+        }
+
+        let mut size: usize = 0;
+        let buf = rquickjs::qjs::JS_WriteObject(
+            raw_ctx,
+            &mut size,
+            compiled,
+            rquickjs::qjs::JS_WRITE_OBJ_BYTECODE as i32,
+        );
+        rquickjs::qjs::JS_FreeValue(raw_ctx, compiled);
+
+        if buf.is_null() {
+            error!("JS_WriteObject failed to serialize the compiled script");
+            return Err(rquickjs::Error::Unknown);
+        }
+
+        let bytes = std::slice::from_raw_parts(buf, size).to_vec();
+        rquickjs::qjs::js_free(rquickjs::qjs::JS_GetRuntime(raw_ctx), buf as *mut std::ffi::c_void);
+        Ok(bytes)
+    }
+}
+
+/// Deserializes a bytecode blob produced by `compile_script` and runs it, skipping
+/// the parser entirely. A malformed blob, or one written by a different QuickJS
+/// build, is rejected by `JS_ReadObject` as a regular thrown exception rather than
+/// aborting the process.
+#[wasm_export]
+pub fn eval_bytecode(ctx: &Ctx<'_>, bytes: Vec<u8>) -> rquickjs::Result<JSJavaProxy> {
+    debug!("Evaluating precompiled bytecode ({} bytes)", bytes.len());
+
+    unsafe {
+        let raw_ctx = ctx.as_raw().as_ptr();
+
+        let compiled = rquickjs::qjs::JS_ReadObject(
+            raw_ctx,
+            bytes.as_ptr(),
+            bytes.len() as _,
+            rquickjs::qjs::JS_READ_OBJ_BYTECODE as i32,
+        );
+
+        if rquickjs::qjs::JS_IsException(compiled) != 0 {
+            rquickjs::qjs::JS_FreeValue(raw_ctx, compiled);
+            error!("JS_ReadObject rejected a malformed or version-mismatched bytecode blob");
+            return Err(rquickjs::Error::Exception);
+        }
+
+        // JS_EvalFunction always consumes (frees) `compiled`, on both success and failure.
+        let result = rquickjs::qjs::JS_EvalFunction(raw_ctx, compiled);
+
+        if rquickjs::qjs::JS_IsException(result) != 0 {
+            rquickjs::qjs::JS_FreeValue(raw_ctx, result);
+            return Err(rquickjs::Error::Exception);
+        }
+
+        let value = Value::from_js_value(ctx.clone(), result);
+        JSJavaProxy::convert(value)
+    }
+}
+
+/// Converts a thrown `rquickjs::Error` into a `JSJavaProxy` exception.
+/// Shared by the native array/object/promise modules for call sites that
+/// are not wrapped by the `wasm_export` macro's own error bridging.
+pub(crate) fn handle_error<'js>(err: rquickjs::Error, ctx: Ctx<'js>) -> JSJavaProxy {
+    JSJavaProxy::from_err(&ctx, err)
+}
+
+/// Drains the runtime's pending-job queue, giving Java a way to deterministically
+/// advance `.then`/`.catch` reactions (e.g. the `JavaPromise` callbacks enqueued by
+/// `promise_resolve`/`promise_reject`) instead of relying on the next unrelated eval.
+///
+/// Returns the number of jobs executed, including jobs that threw: a throwing job
+/// is logged and recorded as the last error via `capture_exception_value`, and
+/// draining continues so a caller can tell progress
+/// was made even if not every job settled cleanly.
+#[wasm_export]
+pub fn context_run_pending_jobs(context: &Context) -> i32 {
+    with_context(context, |ctx| {
+        let mut executed = 0;
+        while ctx.execute_pending_job() {
+            executed += 1;
+            let caught = ctx.catch();
+            if !caught.is_undefined() {
+                error!("Pending job threw while draining the job queue");
+                crate::from_error::capture_exception_value(&ctx, caught);
+            }
+        }
+        executed
+    })
+}
+
+/// Reports whether the runtime still has jobs (promise reactions) waiting to run.
+#[wasm_export]
+pub fn context_has_pending_jobs(context: &Context) -> bool {
+    with_context(context, |ctx| ctx.is_job_pending())
+}
+
 /// Invokes a function in the QuickJS context.
 #[wasm_export]
 pub fn invoke(ctx: &Ctx<'_>, name: String, args: JSJavaProxy) -> rquickjs::Result<JSJavaProxy> {
@@ -1,5 +1,5 @@
 use log::error;
-use rquickjs::Ctx;
+use rquickjs::{Ctx, Exception, Value};
 
 use crate::js_to_java_proxy::JSJavaProxy;
 
@@ -9,46 +9,78 @@ pub trait FromError<'js>: Sized {
     fn from_err(ctx: &Ctx<'js>, err: rquickjs::Error) -> Self;
 }
 
+/// Builds a full `JSJavaProxy::Exception` (class name, message, source-mapped stack,
+/// recursive `cause` chain) from an already-caught value, and records it in the
+/// `Context`'s last-error slot before handing it back. Split out from
+/// `capture_exception` so a caller that already holds the caught value (e.g.
+/// `context_run_pending_jobs`, draining one job at a time) can build the proxy
+/// directly instead of calling `ctx.catch()` a second time, which would find
+/// nothing - the first call already consumed it.
+pub(crate) fn capture_exception_value<'js>(ctx: &Ctx<'js>, value: rquickjs::Value<'js>) -> JSJavaProxy {
+    let proxy = if let Some(exception) = value.as_exception() {
+        let name = exception
+            .get::<_, String>("name")
+            .unwrap_or_else(|_| "Error".to_string());
+        let message = exception.message().unwrap_or_default();
+        let stacktrace = crate::source_map::rewrite_stacktrace(ctx, &exception.stack().unwrap_or_default());
+        let cause = read_cause(exception);
+        JSJavaProxy::Exception {
+            name,
+            message,
+            stacktrace,
+            cause,
+        }
+    } else {
+        JSJavaProxy::error("Unknown exception", String::new())
+    };
+
+    crate::last_error::set_last_error(ctx, proxy.clone());
+    proxy
+}
+
+/// Builds a full `JSJavaProxy::Exception` from the error that just failed, and
+/// records it in the `Context`'s last-error slot before handing it back. Every
+/// `FromError` impl below goes through this, so a caller stuck with a lossy sentinel
+/// return (`-1`, `false`, `None`) can still recover the full diagnostics afterwards
+/// via `take_last_error`.
+fn capture_exception<'js>(ctx: &Ctx<'js>, err: rquickjs::Error) -> JSJavaProxy {
+    match err {
+        rquickjs::Error::Exception => capture_exception_value(ctx, ctx.catch()),
+        _ => {
+            let proxy = JSJavaProxy::error(err.to_string(), String::new());
+            crate::last_error::set_last_error(ctx, proxy.clone());
+            proxy
+        }
+    }
+}
+
+/// Walks the JS error's `cause` property (ES2022 `Error` cause chaining), converting
+/// it to a `JSJavaProxy` so the whole chain survives the round trip to Java, not just
+/// the top-level message. `cause` doesn't have to be an `Error` itself, so this falls
+/// back to `JSJavaProxy::convert`'s generic handling when it isn't.
+fn read_cause<'js>(exception: &Exception<'js>) -> Option<Box<JSJavaProxy>> {
+    let cause: Value = exception.get("cause").ok()?;
+    if cause.is_undefined() {
+        return None;
+    }
+    JSJavaProxy::convert(cause).ok().map(Box::new)
+}
+
 /// Converts a rquickjs::Error into a JSJavaProxy that can be returned to Java
 ///
 impl<'js> FromError<'js> for JSJavaProxy {
     fn from_err(ctx: &Ctx<'js>, err: rquickjs::Error) -> Self {
-        match err {
-            rquickjs::Error::Exception => {
-                let catch = ctx.catch();
-                if let Some(exception) = catch.as_exception() {
-                    let message = exception.message().unwrap();
-                    let stacktrace = exception.stack().unwrap();
-                    JSJavaProxy::Exception(message, stacktrace)
-                } else {
-                    JSJavaProxy::Exception(err.to_string(), String::new())
-                }
-            }
-            _ => JSJavaProxy::Exception(err.to_string(), String::new()),
-        }
+        capture_exception(ctx, err)
     }
 }
 
 impl<'js, T> FromError<'js> for Option<Box<T>> {
     fn from_err(ctx: &Ctx<'js>, err: rquickjs::Error) -> Self {
-        match err {
-            rquickjs::Error::Exception => {
-                let catch = ctx.catch();
-                if let Some(exception) = catch.as_exception() {
-                    let message = exception.message().unwrap();
-                    let stacktrace = exception.stack().unwrap();
-                    error!("Failed to call js {}: {}", message, stacktrace);
-                    None
-                } else {
-                    error!("Failed to call js {}", err.to_string());
-                    None
-                }
-            }
-            _ => {
-                error!("Failed to call js {}", err.to_string());
-                None
-            }
+        let proxy = capture_exception(ctx, err);
+        if let JSJavaProxy::Exception { message, stacktrace, .. } = &proxy {
+            error!("Failed to call js {}: {}", message, stacktrace);
         }
+        None
     }
 }
 
@@ -56,24 +88,24 @@ impl<'js, T> FromError<'js> for Option<Box<T>> {
 ///
 impl<'js> FromError<'js> for bool {
     fn from_err(ctx: &Ctx<'js>, err: rquickjs::Error) -> Self {
-        match err {
-            rquickjs::Error::Exception => {
-                let catch = ctx.catch();
-                if let Some(exception) = catch.as_exception() {
-                    let message = exception.message().unwrap();
-                    let stacktrace = exception.stack().unwrap();
-                    error!("Failed to call js {}: {}", message, stacktrace);
-                    false
-                } else {
-                    error!("Failed to call js {}", err.to_string());
-                    false
-                }
-            }
-            _ => {
-                error!("Failed to call js {}", err.to_string());
-                false
-            }
+        let proxy = capture_exception(ctx, err);
+        if let JSJavaProxy::Exception { message, stacktrace, .. } = &proxy {
+            error!("Failed to call js {}: {}", message, stacktrace);
+        }
+        false
+    }
+}
+
+/// Converts a rquickjs::Error into an empty Vec<u8> that can be returned to Java, for
+/// call sites (e.g. `compile_script`) whose success value is a raw byte blob with no
+/// sentinel value of its own - an empty blob signals failure, with the real cause logged.
+impl<'js> FromError<'js> for Vec<u8> {
+    fn from_err(ctx: &Ctx<'js>, err: rquickjs::Error) -> Self {
+        let proxy = capture_exception(ctx, err);
+        if let JSJavaProxy::Exception { message, stacktrace, .. } = &proxy {
+            error!("Failed to compile/read bytecode: {}: {}", message, stacktrace);
         }
+        Vec::new()
     }
 }
 
@@ -81,23 +113,10 @@ impl<'js> FromError<'js> for bool {
 ///
 impl<'js> FromError<'js> for i32 {
     fn from_err(ctx: &Ctx<'js>, err: rquickjs::Error) -> Self {
-        match err {
-            rquickjs::Error::Exception => {
-                let catch = ctx.catch();
-                if let Some(exception) = catch.as_exception() {
-                    let message = exception.message().unwrap();
-                    let stacktrace = exception.stack().unwrap();
-                    error!("Failed to call js {}: {}", message, stacktrace);
-                    -1
-                } else {
-                    error!("Failed to call js {}", err.to_string());
-                    -1
-                }
-            }
-            _ => {
-                error!("Failed to call js {}", err.to_string());
-                -1
-            }
+        let proxy = capture_exception(ctx, err);
+        if let JSJavaProxy::Exception { message, stacktrace, .. } = &proxy {
+            error!("Failed to call js {}: {}", message, stacktrace);
         }
+        -1
     }
 }
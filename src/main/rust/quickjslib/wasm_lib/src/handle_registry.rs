@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+
+use rquickjs::{Array, Context, Function, JsLifetime, Object, Persistent};
+use wasm_macros::wasm_export;
+
+use crate::completable_future::PromiseContainer;
+use crate::context::with_context;
+
+/// A value owned by the registry, reachable only through a checked handle.
+pub enum RegisteredHandle {
+    Array(Persistent<Array<'static>>),
+    Object(Persistent<Object<'static>>),
+    Function(Persistent<Function<'static>>),
+    Promise(Box<PromiseContainer>),
+}
+
+struct Slot {
+    generation: u32,
+    value: Option<RegisteredHandle>,
+}
+
+/// Owns the boxed `Persistent`s that used to be handed to Java as raw, never-reclaimed
+/// pointers (`NativeArray`, `NativeObject`, `Function`, `CompletableFuture`). Handles
+/// are generational: a 32-bit slot index packed with a 32-bit generation counter into
+/// a `u64`, so a stale or double-freed handle fails a checked lookup instead of
+/// dereferencing freed memory. Stored as `Ctx` userdata, one registry per `Context`.
+#[derive(Default)]
+pub struct HandleRegistry {
+    slots: RefCell<Vec<Slot>>,
+    free: RefCell<Vec<u32>>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for HandleRegistry {
+    type Changed<'to> = HandleRegistry;
+}
+
+impl HandleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `value`, returning the generational handle Java should hold and later
+    /// pass to `free_native_handle`.
+    pub fn register(&self, value: RegisteredHandle) -> u64 {
+        let mut slots = self.slots.borrow_mut();
+
+        let index = match self.free.borrow_mut().pop() {
+            Some(index) => index,
+            None => {
+                slots.push(Slot {
+                    generation: 0,
+                    value: None,
+                });
+                (slots.len() - 1) as u32
+            }
+        };
+
+        let slot = &mut slots[index as usize];
+        slot.value = Some(value);
+        Self::pack(index, slot.generation)
+    }
+
+    /// Looks up the value behind `handle` and runs `f` on it. Returns `None` if the
+    /// slot is empty or the handle's generation is stale (already freed).
+    pub fn with<R>(&self, handle: u64, f: impl FnOnce(&RegisteredHandle) -> R) -> Option<R> {
+        let (index, generation) = Self::unpack(handle);
+        let slots = self.slots.borrow();
+        let slot = slots.get(index as usize)?;
+        if slot.generation != generation {
+            return None;
+        }
+        slot.value.as_ref().map(f)
+    }
+
+    /// Frees the slot behind `handle`, bumping its generation so the handle (and any
+    /// copy of it) becomes detectably stale. Returns `false` for an unknown/stale/
+    /// already-freed handle (a double free).
+    pub fn free(&self, handle: u64) -> bool {
+        let (index, generation) = Self::unpack(handle);
+        let mut slots = self.slots.borrow_mut();
+        match slots.get_mut(index as usize) {
+            Some(slot) if slot.generation == generation && slot.value.is_some() => {
+                slot.value = None;
+                slot.generation = slot.generation.wrapping_add(1);
+                self.free.borrow_mut().push(index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn pack(index: u32, generation: u32) -> u64 {
+        ((index as u64) << 32) | generation as u64
+    }
+
+    fn unpack(handle: u64) -> (u32, u32) {
+        ((handle >> 32) as u32, handle as u32)
+    }
+}
+
+/// Frees a handle previously returned as a `NativeArray`, `NativeObject`, `Function`
+/// or `CompletableFuture` pointer, for the Java side to call from
+/// `AutoCloseable`/`Cleaner`. Returns `false` for an unknown or already-freed handle.
+#[wasm_export]
+pub fn free_native_handle(context: &Context, handle: u64) -> bool {
+    with_context(context, |ctx| match ctx.userdata::<HandleRegistry>() {
+        Some(registry) => registry.free(handle),
+        None => false,
+    })
+}
@@ -47,6 +47,18 @@ impl IntoWasmResult for String {
     }
 }
 
+/// Converts a Vec<u8> into a u64 that can be returned to Java (by returning the pointer to
+/// the bytes and their length, like String). Used for opaque binary blobs - e.g. compiled
+/// QuickJS bytecode - that are handed back raw instead of being MsgPack-encoded.
+impl IntoWasmResult for Vec<u8> {
+    fn into_wasm(self) -> u64 {
+        let len = self.len();
+        let ptr = self.as_ptr();
+        std::mem::forget(self); // Prevent drop, Java reclaims via dealloc
+        ((ptr as u64) << 32) | (len as u64)
+    }
+}
+
 /// Converts a bool into a u64 that can be returned to Java (by returning 1 for true and 0 for false)
 impl IntoWasmResult for bool {
     fn into_wasm(self) -> u64 {
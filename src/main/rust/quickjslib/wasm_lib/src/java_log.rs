@@ -1,6 +1,10 @@
 use log::{Level, LevelFilter};
+use rquickjs::function::{IntoJsFunc, ParamRequirement, Params};
+use rquickjs::{Ctx, Function, Object, Value};
 use wasm_macros::wasm_export;
 
+use crate::js_to_java_proxy::JSJavaProxy;
+
 struct JavaLog {
     level: Level,
 }
@@ -83,3 +87,126 @@ pub fn init_logger(lvl: i32) {
     log::set_boxed_logger(Box::new(logger)).unwrap();
     log::set_max_level(filter);
 }
+
+/// Maximum nesting depth `console_format` will walk into arrays/objects, guarding
+/// against cyclic structures the way `array_codec`'s encoder does.
+const MAX_FORMAT_DEPTH: u32 = 8;
+
+/// Renders a single `console.*` argument the way a browser console would: strings
+/// print without surrounding quotes, arrays/objects are rendered by walking the
+/// `Value` directly, down to `MAX_FORMAT_DEPTH` levels. Deliberately does not go through `JSJavaProxy::convert` -
+/// that would register a `HandleRegistry` handle per array/object/function argument
+/// that nothing here would ever free, and would print nothing more useful than the
+/// raw handle integer anyway.
+fn console_format(value: &Value, depth: u32) -> String {
+    if value.is_undefined() {
+        "undefined".to_string()
+    } else if value.is_null() {
+        "null".to_string()
+    } else if value.is_bool() {
+        value.as_bool().unwrap().to_string()
+    } else if value.is_int() {
+        value.as_int().unwrap().to_string()
+    } else if value.is_float() {
+        value.as_float().unwrap().to_string()
+    } else if value.is_string() {
+        value.as_string().unwrap().to_string().unwrap_or_default()
+    } else if value.is_function() {
+        let function = value.as_function().unwrap();
+        let name = function.get::<_, String>("name").unwrap_or_default();
+        format!("[Function: {}]", if name.is_empty() { "anonymous" } else { &name })
+    } else if value.is_array() {
+        if depth >= MAX_FORMAT_DEPTH {
+            return "[Array]".to_string();
+        }
+        let array = value.as_array().unwrap();
+        let parts: Vec<String> = (0..array.len())
+            .map(|i| {
+                array
+                    .get::<Value>(i)
+                    .map(|v| console_format(&v, depth + 1))
+                    .unwrap_or_else(|_| "undefined".to_string())
+            })
+            .collect();
+        format!("[ {} ]", parts.join(", "))
+    } else if value.is_object() {
+        if depth >= MAX_FORMAT_DEPTH {
+            return "[Object]".to_string();
+        }
+        let object = value.as_object().unwrap();
+        let parts: Vec<String> = object
+            .keys::<String>()
+            .filter_map(|key| key.ok())
+            .map(|key| {
+                let entry = object
+                    .get::<_, Value>(&key)
+                    .map(|v| console_format(&v, depth + 1))
+                    .unwrap_or_else(|_| "undefined".to_string());
+                format!("{}: {}", key, entry)
+            })
+            .collect();
+        format!("{{ {} }}", parts.join(", "))
+    } else {
+        // symbol and any other leaf this crate has no richer rendering for.
+        "undefined".to_string()
+    }
+}
+
+/// A `console.*` method bound to one `log` level: stringifies its variadic
+/// arguments space-joined like a browser console, then forwards the result
+/// through the matching `log` macro so it flows to Java via `JavaLog`/`log_java`.
+struct ConsoleMethod {
+    level: Level,
+}
+
+impl<'js, P> IntoJsFunc<'js, P> for ConsoleMethod {
+    fn param_requirements() -> ParamRequirement {
+        ParamRequirement::any()
+    }
+
+    fn call<'a>(&self, params: Params<'a, 'js>) -> rquickjs::Result<Value<'js>> {
+        let mut parts = Vec::with_capacity(params.len());
+        for i in 0..params.len() {
+            if let Some(v) = params.arg(i) {
+                parts.push(console_format(&v, 0));
+            }
+        }
+        let message = parts.join(" ");
+
+        match self.level {
+            Level::Error => log::error!("{}", message),
+            Level::Warn => log::warn!("{}", message),
+            Level::Info => log::info!("{}", message),
+            Level::Debug => log::debug!("{}", message),
+            Level::Trace => log::trace!("{}", message),
+        }
+
+        Ok(Value::new_undefined(params.ctx().clone()))
+    }
+}
+
+/// Installs a `console` global whose `log`/`info`/`warn`/`error`/`debug`/`trace`
+/// methods route through the `log` crate at the matching level, so script
+/// diagnostics are captured by whatever `JavaLog` sink `init_logger` configured
+/// instead of being silently dropped.
+#[wasm_export]
+pub fn setup_console(ctx: &Ctx<'_>) -> rquickjs::Result<bool> {
+    let console = Object::new(ctx.clone())?;
+
+    let methods: [(&str, Level); 6] = [
+        ("log", Level::Info),
+        ("info", Level::Info),
+        ("warn", Level::Warn),
+        ("error", Level::Error),
+        ("debug", Level::Debug),
+        ("trace", Level::Trace),
+    ];
+
+    for (name, level) in methods {
+        let func = Function::new::<JSJavaProxy, ConsoleMethod>(ctx.clone(), ConsoleMethod { level })?;
+        console.set(name, func)?;
+    }
+
+    ctx.globals().set("console", console)?;
+    Ok(true)
+}
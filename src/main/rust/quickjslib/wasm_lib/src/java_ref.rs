@@ -0,0 +1,331 @@
+use log::error;
+use rquickjs::function::{IntoJsFunc, ParamRequirement, Params};
+use rquickjs::prelude::This;
+use rquickjs::{Ctx, Function, IntoJs, Object, Proxy, Value};
+use wasm_macros::wasm_export;
+
+use crate::js_to_java_proxy::JSJavaProxy;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    pub fn java_proxy_get(context_ptr: u64, handle: i32, prop_ptr: *const u8, prop_len: usize) -> i64;
+    pub fn java_proxy_set(
+        context_ptr: u64,
+        handle: i32,
+        prop_ptr: *const u8,
+        prop_len: usize,
+        value_ptr: *const u8,
+        value_len: usize,
+    ) -> i64;
+    pub fn java_proxy_has(context_ptr: u64, handle: i32, prop_ptr: *const u8, prop_len: usize) -> i64;
+    pub fn java_proxy_apply(context_ptr: u64, handle: i32, args_ptr: *const u8, args_len: usize) -> i64;
+    pub fn java_proxy_own_keys(context_ptr: u64, handle: i32) -> i64;
+    /// Tells Java that the script side no longer holds (or ever held live, in the GC
+    /// sense) a reference to `handle`, so it can release its side of the pairing.
+    /// Called from the `FinalizationRegistry` callback `into_proxy` registers, not
+    /// directly by script code.
+    pub fn java_ref_finalize(context_ptr: u64, handle: i32);
+}
+
+/// Decodes a packed `(ptr << 32) | len` pointer, as returned by every `java_proxy_*`
+/// extern, into the `JSJavaProxy` it points at - the same convention
+/// `JavaFunction`/`call_java_function` use for their own results.
+fn decode_proxy_result(packed: i64) -> JSJavaProxy {
+    let packed = packed as u64;
+    let ptr = (packed >> 32) as usize;
+    let len = (packed & 0xFFFF_FFFF) as usize;
+    let bytes = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+    match rmp_serde::from_slice(bytes) {
+        Ok(result) => result,
+        Err(e) => {
+            error!("MsgPack decode of java_proxy_* result failed: {}", e);
+            JSJavaProxy::Undefined
+        }
+    }
+}
+
+/// Serializes `value` for a ptr-len extern argument. The caller is responsible for
+/// the returned bytes being read before anything else runs on this thread, same as
+/// every other `rmp_serde::to_vec` + `mem::forget` call site in this crate.
+fn encode_arg(value: &JSJavaProxy) -> (*const u8, usize) {
+    let bytes = rmp_serde::to_vec(value).expect("MsgPack encode failed");
+    let len = bytes.len();
+    let ptr = bytes.as_ptr();
+    std::mem::forget(bytes); // Prevent drop, Java reclaims via dealloc
+    (ptr, len)
+}
+
+fn property_name<'js>(value: Option<Value<'js>>) -> String {
+    value
+        .and_then(|v| v.as_string().and_then(|s| s.to_string().ok()))
+        .unwrap_or_default()
+}
+
+/// Lazy view of a Java object exposed through a QuickJS `Proxy`: each trap marshals
+/// only the touched property/argument to Java via one of the `java_proxy_*` externs.
+/// Unlike `JSJavaProxy::JavaObject`, whose proxy traps only `get`/`set` over the
+/// `call_java_function` channel, this also traps `has`/`apply`/`ownKeys` and tells
+/// Java via `java_ref_finalize` once the proxy is collected. Suited to large or
+/// cyclic Java objects that a script only ever touches partially.
+pub struct JavaRef {
+    context_ptr: u64,
+    handle: i32,
+}
+
+impl JavaRef {
+    pub fn new(context_ptr: u64, handle: i32) -> Self {
+        Self { context_ptr, handle }
+    }
+
+    pub fn into_proxy<'js>(self, ctx: &Ctx<'js>) -> rquickjs::Result<Value<'js>> {
+        // The target must be callable for the `apply` trap to ever fire, so use a
+        // bare function rather than a plain object.
+        let target: Function = ctx.eval("(function () {})")?;
+        let handler = Object::new(ctx.clone())?;
+
+        handler.set(
+            "get",
+            Function::new::<JSJavaProxy, JavaRefGetTrap>(
+                ctx.clone(),
+                JavaRefGetTrap { context_ptr: self.context_ptr, handle: self.handle },
+            )?,
+        )?;
+        handler.set(
+            "set",
+            Function::new::<JSJavaProxy, JavaRefSetTrap>(
+                ctx.clone(),
+                JavaRefSetTrap { context_ptr: self.context_ptr, handle: self.handle },
+            )?,
+        )?;
+        handler.set(
+            "has",
+            Function::new::<JSJavaProxy, JavaRefHasTrap>(
+                ctx.clone(),
+                JavaRefHasTrap { context_ptr: self.context_ptr, handle: self.handle },
+            )?,
+        )?;
+        handler.set(
+            "apply",
+            Function::new::<JSJavaProxy, JavaRefApplyTrap>(
+                ctx.clone(),
+                JavaRefApplyTrap { context_ptr: self.context_ptr, handle: self.handle },
+            )?,
+        )?;
+        handler.set(
+            "ownKeys",
+            Function::new::<JSJavaProxy, JavaRefOwnKeysTrap>(
+                ctx.clone(),
+                JavaRefOwnKeysTrap { context_ptr: self.context_ptr, handle: self.handle },
+            )?,
+        )?;
+
+        let proxy = Proxy::new(target, handler)?;
+        let proxy = proxy.into_value();
+
+        register_finalizer(ctx, &proxy, self.context_ptr, self.handle)?;
+
+        Ok(proxy)
+    }
+}
+
+/// Private field on the globals holding the shared `FinalizationRegistry` used to
+/// notify Java once a `JavaRef` proxy becomes unreachable to script - lazily created
+/// once per `Context` and reused, since an unregistered, otherwise-unreferenced
+/// `FinalizationRegistry` is itself free to be collected before its callback fires.
+static FINALIZATION_REGISTRY_FIELD: &str = "___java_ref_finalization_registry";
+
+/// Registers `proxy` with the shared `FinalizationRegistry`, held value `handle`, so
+/// `java_ref_finalize` is called once script drops the last reference to `proxy` and
+/// it is collected - giving Java an actual release point instead of leaking for the
+/// life of the runtime.
+fn register_finalizer<'js>(ctx: &Ctx<'js>, proxy: &Value<'js>, context_ptr: u64, handle: i32) -> rquickjs::Result<()> {
+    let registry = finalization_registry(ctx, context_ptr)?;
+    let register: Function = registry.get("register")?;
+    register.call::<_, ()>((This(registry.clone()), proxy.clone(), handle))
+}
+
+fn finalization_registry<'js>(ctx: &Ctx<'js>, context_ptr: u64) -> rquickjs::Result<Object<'js>> {
+    let globals = ctx.globals();
+    if let Ok(registry) = globals.get::<_, Object>(FINALIZATION_REGISTRY_FIELD) {
+        return Ok(registry);
+    }
+
+    let callback = Function::new::<JSJavaProxy, JavaRefFinalizeTrap>(ctx.clone(), JavaRefFinalizeTrap { context_ptr })?;
+    let ctor: Function = globals.get("FinalizationRegistry")?;
+    let registry: Object = ctor.construct((callback,))?;
+    globals.set(FINALIZATION_REGISTRY_FIELD, registry.clone())?;
+    Ok(registry)
+}
+
+/// `FinalizationRegistry` callback invoked by the engine once a registered `JavaRef`
+/// proxy is collected, with the registered `handle` as its held value.
+struct JavaRefFinalizeTrap {
+    context_ptr: u64,
+}
+
+impl<'js, P> IntoJsFunc<'js, P> for JavaRefFinalizeTrap {
+    fn param_requirements() -> ParamRequirement {
+        ParamRequirement::single()
+    }
+
+    fn call<'a>(&self, params: Params<'a, 'js>) -> rquickjs::Result<Value<'js>> {
+        if let Some(handle) = params.arg(0).and_then(|v| v.as_int()) {
+            unsafe { java_ref_finalize(self.context_ptr, handle) };
+        }
+        Ok(Value::new_undefined(params.ctx().clone()))
+    }
+}
+
+/// `get(target, property, receiver)` trap.
+struct JavaRefGetTrap {
+    context_ptr: u64,
+    handle: i32,
+}
+
+impl<'js, P> IntoJsFunc<'js, P> for JavaRefGetTrap {
+    fn param_requirements() -> ParamRequirement {
+        ParamRequirement::any()
+    }
+
+    fn call<'a>(&self, params: Params<'a, 'js>) -> rquickjs::Result<Value<'js>> {
+        let prop = property_name(params.arg(1));
+        let prop_bytes = prop.as_bytes();
+        let packed = unsafe {
+            java_proxy_get(self.context_ptr, self.handle, prop_bytes.as_ptr(), prop_bytes.len())
+        };
+        decode_proxy_result(packed).into_js(params.ctx())
+    }
+}
+
+/// `set(target, property, value, receiver)` trap. A Java-side write failure
+/// surfaces as a thrown exception instead of a boolean return, same as
+/// `JavaObject`'s `set` trap.
+struct JavaRefSetTrap {
+    context_ptr: u64,
+    handle: i32,
+}
+
+impl<'js, P> IntoJsFunc<'js, P> for JavaRefSetTrap {
+    fn param_requirements() -> ParamRequirement {
+        ParamRequirement::any()
+    }
+
+    fn call<'a>(&self, params: Params<'a, 'js>) -> rquickjs::Result<Value<'js>> {
+        let prop = property_name(params.arg(1));
+        let value = match params.arg(2) {
+            Some(v) => JSJavaProxy::convert(v)?,
+            None => JSJavaProxy::Undefined,
+        };
+
+        let prop_bytes = prop.as_bytes();
+        let (value_ptr, value_len) = encode_arg(&value);
+        let packed = unsafe {
+            java_proxy_set(
+                self.context_ptr,
+                self.handle,
+                prop_bytes.as_ptr(),
+                prop_bytes.len(),
+                value_ptr,
+                value_len,
+            )
+        };
+
+        let result = decode_proxy_result(packed);
+        let is_exception = matches!(result, JSJavaProxy::Exception { .. });
+        let js_value = result.into_js(params.ctx())?;
+        if is_exception {
+            Err(params.ctx().throw(js_value))
+        } else {
+            Ok(Value::new_bool(params.ctx().clone(), true))
+        }
+    }
+}
+
+/// `has(target, property)` trap.
+struct JavaRefHasTrap {
+    context_ptr: u64,
+    handle: i32,
+}
+
+impl<'js, P> IntoJsFunc<'js, P> for JavaRefHasTrap {
+    fn param_requirements() -> ParamRequirement {
+        ParamRequirement::any()
+    }
+
+    fn call<'a>(&self, params: Params<'a, 'js>) -> rquickjs::Result<Value<'js>> {
+        let prop = property_name(params.arg(1));
+        let prop_bytes = prop.as_bytes();
+        let packed = unsafe {
+            java_proxy_has(self.context_ptr, self.handle, prop_bytes.as_ptr(), prop_bytes.len())
+        };
+        let has = matches!(decode_proxy_result(packed), JSJavaProxy::Boolean(true));
+        Ok(Value::new_bool(params.ctx().clone(), has))
+    }
+}
+
+/// `apply(target, thisArg, argumentsList)` trap: only the (already array-like)
+/// `argumentsList` is forwarded, converted the same way a `JavaFunction` call's
+/// arguments are.
+struct JavaRefApplyTrap {
+    context_ptr: u64,
+    handle: i32,
+}
+
+impl<'js, P> IntoJsFunc<'js, P> for JavaRefApplyTrap {
+    fn param_requirements() -> ParamRequirement {
+        ParamRequirement::any()
+    }
+
+    fn call<'a>(&self, params: Params<'a, 'js>) -> rquickjs::Result<Value<'js>> {
+        let args = match params.arg(2) {
+            Some(v) => JSJavaProxy::convert(v)?,
+            None => JSJavaProxy::Array(Vec::new()),
+        };
+        let (args_ptr, args_len) = encode_arg(&args);
+        let packed = unsafe { java_proxy_apply(self.context_ptr, self.handle, args_ptr, args_len) };
+
+        let result = decode_proxy_result(packed);
+        let is_exception = matches!(result, JSJavaProxy::Exception { .. });
+        let value = result.into_js(params.ctx())?;
+        if is_exception {
+            Err(params.ctx().throw(value))
+        } else {
+            Ok(value)
+        }
+    }
+}
+
+/// `ownKeys(target)` trap: Java returns a `JSJavaProxy::Array` of property name
+/// strings, which `into_js` turns into the JS array the trap must return.
+struct JavaRefOwnKeysTrap {
+    context_ptr: u64,
+    handle: i32,
+}
+
+impl<'js, P> IntoJsFunc<'js, P> for JavaRefOwnKeysTrap {
+    fn param_requirements() -> ParamRequirement {
+        ParamRequirement::any()
+    }
+
+    fn call<'a>(&self, _params: Params<'a, 'js>) -> rquickjs::Result<Value<'js>> {
+        let packed = unsafe { java_proxy_own_keys(self.context_ptr, self.handle) };
+        decode_proxy_result(packed).into_js(_params.ctx())
+    }
+}
+
+/// Wraps `handle` - an opaque Java-side object reference - as a `JSJavaProxy::JavaRef`,
+/// so it can be returned to a script and, once it crosses back into JS (`into_js`),
+/// becomes a live `Proxy` backed by the `java_proxy_*` externs above.
+#[wasm_export]
+pub fn wrap_java_object(_ctx: &Ctx<'_>, handle: i32) -> JSJavaProxy {
+    JSJavaProxy::JavaRef(handle)
+}
+
+/// Callable by Java ahead of the script-side GC reclaiming the proxy (e.g. if Java
+/// itself already knows `handle` is done with), to release eagerly instead of
+/// waiting on `java_ref_finalize`. There is no Rust-side state to free either way -
+/// the handle is an opaque token Java already owns - so this only exists to give the
+/// host an explicit, synchronous release point alongside the `FinalizationRegistry`-
+/// driven one `into_proxy` sets up.
+#[wasm_export]
+pub fn release_java_ref(_handle: i32) {}
@@ -6,6 +6,7 @@ use rquickjs::function::IntoJsFunc;
 use rquickjs::function::ParamRequirement;
 use rquickjs::prelude::IntoArgs;
 use rquickjs::Array;
+use rquickjs::ArrayBuffer;
 use rquickjs::Atom;
 use rquickjs::FromAtom;
 use rquickjs::FromJs;
@@ -15,15 +16,42 @@ use rquickjs::IntoJs;
 use rquickjs::Object;
 use rquickjs::Persistent;
 use rquickjs::Promise;
+use rquickjs::Proxy;
 use rquickjs::Value;
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::HashMap;
 
 use crate::completable_future::convert_promise;
+use crate::handle_registry::{HandleRegistry, RegisteredHandle};
 use crate::quickjs_function::call_java_function;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// Registers `value` in the `Context`'s `HandleRegistry`, returning the generational
+/// handle to hand to Java. This is the checked counterpart to the raw
+/// `Box::into_raw(Box::new(Persistent::save(...)))` pattern: the registry owns the
+/// `Persistent` and the handle can be validated (and freed) instead of blindly
+/// dereferenced.
+pub(crate) fn register_handle<'js>(
+    ctx: &rquickjs::Ctx<'js>,
+    value: RegisteredHandle,
+) -> rquickjs::Result<u64> {
+    let registry = ctx.userdata::<HandleRegistry>().ok_or(rquickjs::Error::Unknown)?;
+    Ok(registry.register(value))
+}
+
+/// Looks up `handle` in the `Context`'s `HandleRegistry` and runs `f` on it, failing
+/// with `rquickjs::Error::Unknown` for a missing registry, or a stale/freed handle,
+/// instead of dereferencing freed memory.
+pub(crate) fn with_registered_handle<'js, R>(
+    ctx: &rquickjs::Ctx<'js>,
+    handle: u64,
+    f: impl FnOnce(&RegisteredHandle) -> rquickjs::Result<R>,
+) -> rquickjs::Result<R> {
+    let registry = ctx.userdata::<HandleRegistry>().ok_or(rquickjs::Error::Unknown)?;
+    registry.with(handle, f).unwrap_or(Err(rquickjs::Error::Unknown))
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum JSJavaProxy {
     Null,
@@ -31,7 +59,12 @@ pub enum JSJavaProxy {
     String(String),
     Int(i32),
     Float(f64),
+    /// Decimal string representation of a JS `BigInt`, for lossless transport as
+    /// `java.math.BigInteger`
+    BigInt(String),
     Boolean(bool),
+    /// Copy of an `ArrayBuffer`/`TypedArray`'s bytes, for mapping to `byte[]`/`ByteBuffer`
+    Bytes(Vec<u8>),
     Array(Vec<JSJavaProxy>),
     /// Fields: Array Pointer
     NativeArray(u64),
@@ -40,10 +73,29 @@ pub enum JSJavaProxy {
     NativeObject(u64),
     /// Fields: Function Name, function pointer
     Function(String, u64),
-    /// Fields: Context, function_ptr
-    JavaFunction(i32, i32),
-    /// Fields: Message, Stacktrace
-    Exception(String, String),
+    /// Fields: Context, function_ptr, is_async. A non-blocking (`is_async == true`)
+    /// function returns a JS `Promise` immediately instead of blocking the calling
+    /// script on `call_java_function`; Java settles it later via `resolve_promise`.
+    JavaFunction(i32, i32, bool),
+    /// Fields: Context, object_ptr
+    JavaObject(i32, i32),
+    /// Fields: handle. An opaque Java-side object reference; once this crosses into
+    /// JS, `into_js` wraps it as a live `Proxy` whose `get`/`set`/`has`/`apply`/
+    /// `ownKeys` traps marshal only the touched property/argument to Java through the
+    /// `java_proxy_*` externs, unlike `JavaObject`'s proxy, which traps only `get`/`set`.
+    JavaRef(i32),
+    /// The JS error's `name` (e.g. "TypeError", "RangeError"), defaulting to "Error"
+    /// when the thrown value had none, so the class survives the round trip and a
+    /// Java handler can branch on it (and, on the way back, `into_js` reconstructs
+    /// the matching global error constructor).
+    Exception {
+        name: String,
+        message: String,
+        stacktrace: String,
+        /// The JS error's `cause` property (ES2022 `Error` cause chaining), converted
+        /// recursively - `None` if the error carried no `cause` at all.
+        cause: Option<Box<JSJavaProxy>>,
+    },
     /// Fields: Pointer to java completable future, Pointer to native promise
     CompletableFuture(i32, u64),
 }
@@ -79,6 +131,14 @@ impl<'js> IntoJs<'js> for JSJavaProxy {
             JSJavaProxy::Undefined => Ok(Value::new_undefined(ctx.clone())),
             JSJavaProxy::Float(value) => Ok(Value::new_float(ctx.clone(), value)),
             JSJavaProxy::Int(value) => Ok(Value::new_int(ctx.clone(), value)),
+            JSJavaProxy::BigInt(value) => {
+                let big_int_ctor: Function = ctx.globals().get("BigInt")?;
+                big_int_ctor.call((value,))
+            }
+            JSJavaProxy::Bytes(bytes) => {
+                let buffer = ArrayBuffer::new(ctx.clone(), bytes)?;
+                Ok(buffer.into_value())
+            }
             JSJavaProxy::Boolean(value) => Ok(Value::new_bool(ctx.clone(), value)),
             JSJavaProxy::String(value) => Ok(Value::from_string(rquickjs::String::from_str(
                 ctx.clone(),
@@ -98,40 +158,94 @@ impl<'js> IntoJs<'js> for JSJavaProxy {
                 }
                 Ok(obj.into_value())
             }
-            JSJavaProxy::Function(_name, ptr) => {
-                let function = unsafe { &*(ptr as *mut Persistent<Function>) };
-                let restored_function = function.clone().restore(ctx)?;
-                Ok(restored_function.into_value())
-            }
-            JSJavaProxy::JavaFunction(ctx_ptr, function_ptr) => {
+            JSJavaProxy::Function(_name, handle) => with_registered_handle(ctx, handle, |h| {
+                match h {
+                    RegisteredHandle::Function(function) => {
+                        let restored_function = function.clone().restore(ctx)?;
+                        Ok(restored_function.into_value())
+                    }
+                    _ => Err(rquickjs::Error::Unknown),
+                }
+            }),
+            JSJavaProxy::JavaFunction(ctx_ptr, function_ptr, is_async) => {
                 debug!(
-                    "Imported Java function: {} at context {}",
-                    function_ptr, ctx_ptr
+                    "Imported Java function: {} at context {} (async: {})",
+                    function_ptr, ctx_ptr, is_async
                 );
-                let f = JavaFunction::new(ctx_ptr, function_ptr);
-                let func = Function::new::<JSJavaProxy, JavaFunction>(ctx.clone(), f)?;
+                let func = if is_async {
+                    Function::new::<JSJavaProxy, crate::async_java_function::AsyncJavaCall>(
+                        ctx.clone(),
+                        crate::async_java_function::AsyncJavaCall::new(ctx_ptr, function_ptr),
+                    )?
+                } else {
+                    let f = JavaFunction::new(ctx_ptr, function_ptr);
+                    Function::new::<JSJavaProxy, JavaFunction>(ctx.clone(), f)?
+                };
                 let s = Value::from_function(func);
                 Ok(s)
             }
-            JSJavaProxy::Exception(msg, _stacktrace) => {
-                let exception = rquickjs::Exception::from_message(ctx.clone(), &msg)?;
-                Ok(exception.into_value())
+            JSJavaProxy::JavaObject(ctx_ptr, object_ptr) => {
+                debug!(
+                    "Imported Java object: {} at context {}",
+                    object_ptr, ctx_ptr
+                );
+                let java_object = JavaObject::new(ctx_ptr, object_ptr);
+                java_object.into_proxy(ctx)
             }
-            JSJavaProxy::NativeArray(pointer) => {
-                let persistent_array = unsafe { &*(pointer as *mut Persistent<Array>) };
-                let array = persistent_array.clone().restore(ctx)?;
-                Ok(array.into_value())
+            JSJavaProxy::JavaRef(handle) => {
+                debug!("Imported lazy Java object reference: {}", handle);
+                let context_ptr = ctx
+                    .userdata::<crate::context::ContextPtr>()
+                    .ok_or(rquickjs::Error::Unknown)?
+                    .ptr;
+                crate::java_ref::JavaRef::new(context_ptr, handle).into_proxy(ctx)
             }
-            JSJavaProxy::NativeObject(pointer) => {
-                let persistent_object = unsafe { &*(pointer as *mut Persistent<Object>) };
-                let object = persistent_object.clone().restore(ctx)?;
-                Ok(object.into_value())
+            JSJavaProxy::Exception {
+                name,
+                message,
+                stacktrace: _,
+                cause,
+            } => {
+                // Reconstruct the matching error class (TypeError, RangeError, ...) so
+                // script-side `instanceof` checks see the original JS error class;
+                // fall back to the plain `Error` constructor for an unknown name.
+                let ctor: Function = ctx
+                    .globals()
+                    .get(name.as_str())
+                    .or_else(|_| ctx.globals().get("Error"))?;
+                match cause {
+                    // ES2022 `Error(message, {cause})` so `err.cause` survives the round trip too.
+                    Some(cause) => {
+                        let options = rquickjs::Object::new(ctx.clone())?;
+                        options.set("cause", (*cause).into_js(ctx)?)?;
+                        ctor.call((message, options))
+                    }
+                    None => ctor.call((message,)),
+                }
             }
-            JSJavaProxy::CompletableFuture(future_ptr, promise_ptr) => {
-                let promise = unsafe { &*(promise_ptr as *mut Persistent<Promise>) };
-                let restored_promise = promise.clone().restore(ctx)?;
-                restored_promise.set("__completable_future_ptr", future_ptr)?;
-                Ok(restored_promise.into_value())
+            JSJavaProxy::NativeArray(handle) => with_registered_handle(ctx, handle, |h| match h {
+                RegisteredHandle::Array(array) => {
+                    let array = array.clone().restore(ctx)?;
+                    Ok(array.into_value())
+                }
+                _ => Err(rquickjs::Error::Unknown),
+            }),
+            JSJavaProxy::NativeObject(handle) => with_registered_handle(ctx, handle, |h| match h {
+                RegisteredHandle::Object(object) => {
+                    let object = object.clone().restore(ctx)?;
+                    Ok(object.into_value())
+                }
+                _ => Err(rquickjs::Error::Unknown),
+            }),
+            JSJavaProxy::CompletableFuture(future_ptr, handle) => {
+                with_registered_handle(ctx, handle, |h| match h {
+                    RegisteredHandle::Promise(container) => {
+                        let restored_promise = container.promise.clone().restore(ctx)?;
+                        restored_promise.set("__completable_future_ptr", future_ptr)?;
+                        Ok(restored_promise.into_value())
+                    }
+                    _ => Err(rquickjs::Error::Unknown),
+                })
             }
         };
         result
@@ -165,6 +279,17 @@ impl<'js> IntoArgs<'js> for JSJavaProxy {
 }
 
 impl JSJavaProxy {
+    /// Builds a plain `Error`-classed exception, for call sites that have a failure
+    /// message but no JS error object to read a more specific `name` off of.
+    pub fn error(message: impl Into<String>, stacktrace: impl Into<String>) -> JSJavaProxy {
+        JSJavaProxy::Exception {
+            name: "Error".to_string(),
+            message: message.into(),
+            stacktrace: stacktrace.into(),
+            cause: None,
+        }
+    }
+
     pub fn convert<'js>(value: Value<'js>) -> rquickjs::Result<JSJavaProxy> {
         if value.is_null() {
             return Ok(JSJavaProxy::Null);
@@ -173,6 +298,23 @@ impl JSJavaProxy {
         } else if value.is_promise() {
             let promise = value.into_promise().unwrap();
             return convert_promise(promise);
+        } else if value.is_big_int() {
+            let big_int = value.as_big_int().unwrap();
+            return Ok(JSJavaProxy::BigInt(big_int.to_string()?));
+        } else if value.is_array_buffer() {
+            debug!("Converting js array buffer to java bytes");
+            let buffer = value.into_array_buffer().unwrap();
+            let bytes = buffer.as_bytes().unwrap_or(&[]).to_vec();
+            return Ok(JSJavaProxy::Bytes(bytes));
+        } else if value.is_typed_array() {
+            debug!("Converting js typed array to java bytes");
+            let obj = value.as_object().unwrap();
+            let buffer: ArrayBuffer = obj.get("buffer")?;
+            let byte_offset: usize = obj.get("byteOffset")?;
+            let byte_length: usize = obj.get("byteLength")?;
+            let full = buffer.as_bytes().unwrap_or(&[]);
+            let bytes = full[byte_offset..byte_offset + byte_length].to_vec();
+            return Ok(JSJavaProxy::Bytes(bytes));
         } else if value.is_function() {
             let function = value.into_function().unwrap();
             let ctx = function.ctx().clone();
@@ -180,10 +322,10 @@ impl JSJavaProxy {
             let name: String = function.get("name")?;
 
             let persistent_function = Persistent::save(&ctx, function);
-            let persistent_function_ptr = Box::into_raw(Box::new(persistent_function)) as u64;
+            let handle = register_handle(&ctx, RegisteredHandle::Function(persistent_function))?;
 
-            debug!("Exported function: {} -> {}", name, persistent_function_ptr);
-            return Ok(JSJavaProxy::Function(name, persistent_function_ptr));
+            debug!("Exported function: {} -> {}", name, handle);
+            return Ok(JSJavaProxy::Function(name, handle));
         } else if value.is_string() {
             let string = value.into_string().clone().unwrap();
             return Ok(JSJavaProxy::String(string.to_string()?));
@@ -199,11 +341,25 @@ impl JSJavaProxy {
         } else if value.is_exception() {
             debug!("Converting js exception to java exception");
             let exception = value.into_exception().unwrap();
+            let name = exception
+                .get::<_, String>("name")
+                .unwrap_or_else(|_| "Error".to_string());
             let message = exception
                 .message()
                 .unwrap_or("<No exception message>".to_string());
             let stacktrace = exception.stack().unwrap_or("<No stacktrace>".to_string());
-            return Ok(JSJavaProxy::Exception(message, stacktrace));
+            let cause = exception
+                .get::<_, Value>("cause")
+                .ok()
+                .filter(|cause| !cause.is_undefined())
+                .and_then(|cause| JSJavaProxy::convert(cause).ok())
+                .map(Box::new);
+            return Ok(JSJavaProxy::Exception {
+                name,
+                message,
+                stacktrace,
+                cause,
+            });
         } else if value.is_array() {
             debug!("Converting js array to java array");
             // create reference to array instead of copying by values
@@ -211,9 +367,9 @@ impl JSJavaProxy {
             let ctx = array.ctx().clone();
 
             let persistent_array = Persistent::save(&ctx, array);
-            let persistent_array_ptr = Box::into_raw(Box::new(persistent_array)) as u64;
-            debug!("Created pointer to native array: {}", persistent_array_ptr);
-            return Ok(JSJavaProxy::NativeArray(persistent_array_ptr));
+            let handle = register_handle(&ctx, RegisteredHandle::Array(persistent_array))?;
+            debug!("Created handle for native array: {}", handle);
+            return Ok(JSJavaProxy::NativeArray(handle));
 
             // let mut vec = Vec::new();
             // for i in 0..array.len() {
@@ -227,12 +383,9 @@ impl JSJavaProxy {
             let ctx = object.ctx().clone();
 
             let persistent_object = Persistent::save(&ctx, object);
-            let persistent_object_ptr = Box::into_raw(Box::new(persistent_object)) as u64;
-            debug!(
-                "Created pointer to native object: {}",
-                persistent_object_ptr
-            );
-            return Ok(JSJavaProxy::NativeObject(persistent_object_ptr));
+            let handle = register_handle(&ctx, RegisteredHandle::Object(persistent_object))?;
+            debug!("Created handle for native object: {}", handle);
+            return Ok(JSJavaProxy::NativeObject(handle));
 
             // let mut map = HashMap::new();
 
@@ -309,6 +462,25 @@ impl JavaFunction {
             call: Box::new(call),
         }
     }
+
+    /// Calls the wrapped Java side with `arg`, converting the result into a JS value
+    /// for `ctx`. A `JSJavaProxy::Exception` result is thrown as a JS exception
+    /// instead of being handed back as a value, matching how a thrown Java exception
+    /// should surface to the calling script.
+    fn invoke<'js>(&self, ctx: &rquickjs::Ctx<'js>, arg: JSJavaProxy) -> rquickjs::Result<Value<'js>> {
+        let result = (self.call)(arg);
+
+        // Reuse `into_js`'s Exception handling so a Java-originated exception is
+        // thrown as the same typed JS error (TypeError, RangeError, ...) a script
+        // would see if it had thrown the error itself.
+        let is_exception = matches!(result, JSJavaProxy::Exception { .. });
+        let value = result.into_js(ctx)?;
+        if is_exception {
+            Err(ctx.throw(value))
+        } else {
+            Ok(value)
+        }
+    }
 }
 
 impl<'js, P> IntoJsFunc<'js, P> for JavaFunction {
@@ -331,16 +503,108 @@ impl<'js, P> IntoJsFunc<'js, P> for JavaFunction {
         }
 
         let arg = JSJavaProxy::Array(args);
-        let result = (self.call)(arg);
+        self.invoke(params.ctx(), arg)
+    }
+}
 
-        // If the result is an exception, throw it
-        if let JSJavaProxy::Exception(message, _stacktrace) = &result {
-            let exception = rquickjs::Exception::from_message(params.ctx().clone(), &message)?;
-            Err(params.ctx().throw(exception.into_value()))
-        } else {
-            result.into_js(params.ctx())
+/// Wraps a Java object pointer as a live JS `Proxy`: `get`/`set` traps on an empty
+/// target object dispatch the property name (and, for `set`, the new value) back to
+/// Java over the same `call_java_function` channel `JavaFunction` already uses, just
+/// keyed by the object pointer instead of a function pointer.
+///
+/// Method calls need no separate trap: a `get` that resolves to a Java method comes
+/// back as a `JSJavaProxy::JavaFunction`, which `into_js` turns into a real bound JS
+/// function exactly like any other imported Java function.
+pub struct JavaObject {
+    resolver: std::rc::Rc<JavaFunction>,
+}
+
+impl JavaObject {
+    pub fn new(context: i32, object: i32) -> Self {
+        Self {
+            resolver: std::rc::Rc::new(JavaFunction::new(context, object)),
         }
     }
+
+    pub fn into_proxy<'js>(self, ctx: &Ctx<'js>) -> rquickjs::Result<Value<'js>> {
+        let target = Object::new(ctx.clone())?;
+        let handler = Object::new(ctx.clone())?;
+
+        let get = Function::new::<JSJavaProxy, JavaObjectGetTrap>(
+            ctx.clone(),
+            JavaObjectGetTrap {
+                resolver: self.resolver.clone(),
+            },
+        )?;
+        let set = Function::new::<JSJavaProxy, JavaObjectSetTrap>(
+            ctx.clone(),
+            JavaObjectSetTrap {
+                resolver: self.resolver.clone(),
+            },
+        )?;
+        handler.set("get", get)?;
+        handler.set("set", set)?;
+
+        let proxy = Proxy::new(target, handler)?;
+        Ok(proxy.into_value())
+    }
+}
+
+/// `get(target, property, receiver)` trap: only the property name is forwarded.
+struct JavaObjectGetTrap {
+    resolver: std::rc::Rc<JavaFunction>,
+}
+
+impl<'js, P> IntoJsFunc<'js, P> for JavaObjectGetTrap {
+    fn param_requirements() -> rquickjs::function::ParamRequirement {
+        ParamRequirement::any()
+    }
+
+    fn call<'a>(
+        &self,
+        params: rquickjs::function::Params<'a, 'js>,
+    ) -> rquickjs::Result<Value<'js>> {
+        let key = match params.arg(1) {
+            Some(v) => JSJavaProxy::convert(v)?,
+            None => JSJavaProxy::Undefined,
+        };
+        self.resolver.invoke(
+            params.ctx(),
+            JSJavaProxy::Array(vec![JSJavaProxy::String("get".to_string()), key]),
+        )
+    }
+}
+
+/// `set(target, property, value, receiver)` trap: property name and new value are
+/// forwarded; the trap itself always reports success back to the JS engine, since
+/// a Java-side write failure surfaces as a thrown exception instead.
+struct JavaObjectSetTrap {
+    resolver: std::rc::Rc<JavaFunction>,
+}
+
+impl<'js, P> IntoJsFunc<'js, P> for JavaObjectSetTrap {
+    fn param_requirements() -> rquickjs::function::ParamRequirement {
+        ParamRequirement::any()
+    }
+
+    fn call<'a>(
+        &self,
+        params: rquickjs::function::Params<'a, 'js>,
+    ) -> rquickjs::Result<Value<'js>> {
+        let key = match params.arg(1) {
+            Some(v) => JSJavaProxy::convert(v)?,
+            None => JSJavaProxy::Undefined,
+        };
+        let value = match params.arg(2) {
+            Some(v) => JSJavaProxy::convert(v)?,
+            None => JSJavaProxy::Undefined,
+        };
+        self.resolver.invoke(
+            params.ctx(),
+            JSJavaProxy::Array(vec![JSJavaProxy::String("set".to_string()), key, value]),
+        )?;
+        Ok(Value::new_bool(params.ctx().clone(), true))
+    }
 }
 
 #[cfg(test)]
@@ -373,6 +637,22 @@ mod tests {
         assert_eq!(value, deserialized);
     }
 
+    #[test]
+    fn test_serde_bigint() {
+        let value = JSJavaProxy::BigInt("123456789012345678901234567890".to_string());
+        let bytes = rmp_serde::to_vec(&value).unwrap();
+        let deserialized: JSJavaProxy = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(value, deserialized);
+    }
+
+    #[test]
+    fn test_serde_bytes() {
+        let value = JSJavaProxy::Bytes(vec![1, 2, 3, 4]);
+        let bytes = rmp_serde::to_vec(&value).unwrap();
+        let deserialized: JSJavaProxy = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(value, deserialized);
+    }
+
     #[test]
     fn test_serde_boolean() {
         let value = JSJavaProxy::Boolean(true);
@@ -451,6 +731,54 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_js_java_proxy_bigint() {
+        let rt = Runtime::new().unwrap();
+        let context = Context::full(&rt).unwrap();
+
+        let result = context.with(|ctx| {
+            let value: JSJavaProxy = ctx.eval("10n ** 40n").unwrap();
+            value
+        });
+
+        match result {
+            JSJavaProxy::BigInt(s) => assert_eq!(s, "10000000000000000000000000000000000000000"),
+            _ => panic!("Expected a bigint"),
+        }
+    }
+
+    #[test]
+    fn test_js_java_proxy_array_buffer() {
+        let rt = Runtime::new().unwrap();
+        let context = Context::full(&rt).unwrap();
+
+        let result = context.with(|ctx| {
+            let value: JSJavaProxy = ctx.eval("new Uint8Array([1, 2, 3]).buffer").unwrap();
+            value
+        });
+
+        match result {
+            JSJavaProxy::Bytes(v) => assert_eq!(v, vec![1, 2, 3]),
+            _ => panic!("Expected bytes"),
+        }
+    }
+
+    #[test]
+    fn test_js_java_proxy_typed_array() {
+        let rt = Runtime::new().unwrap();
+        let context = Context::full(&rt).unwrap();
+
+        let result = context.with(|ctx| {
+            let value: JSJavaProxy = ctx.eval("new Uint8Array([1, 2, 3])").unwrap();
+            value
+        });
+
+        match result {
+            JSJavaProxy::Bytes(v) => assert_eq!(v, vec![1, 2, 3]),
+            _ => panic!("Expected bytes"),
+        }
+    }
+
     #[test]
     fn test_js_java_proxy_string() {
         let rt = Runtime::new().unwrap();
@@ -561,6 +889,9 @@ mod tests {
     fn test_js_java_proxy_function() {
         let rt = Runtime::new().unwrap();
         let context = Context::full(&rt).unwrap();
+        context.with(|ctx| {
+            let _ = ctx.store_userdata(HandleRegistry::new());
+        });
 
         let result: JSJavaProxy =
             context.with(|ctx| match ctx.eval("function a() { return 1; };a") {
@@ -568,16 +899,18 @@ mod tests {
                 Err(e) => panic!("Error evaluating script: {}", e),
             });
 
-        // Try to restorce peristent function from result
+        // Try to restore the function behind the registry handle
         match result {
-            JSJavaProxy::Function(_name, ptr) => {
-                let persistent_function =
-                    unsafe { Box::from_raw(ptr as *mut Persistent<Function>) };
-
+            JSJavaProxy::Function(_name, handle) => {
                 let result = context.with(|ctx| {
-                    let function = persistent_function.clone().restore(&ctx).unwrap();
-                    let result: JSJavaProxy = function.call(()).unwrap();
-                    result
+                    with_registered_handle(&ctx, handle, |h| match h {
+                        RegisteredHandle::Function(function) => {
+                            let function = function.clone().restore(&ctx)?;
+                            function.call(())
+                        }
+                        _ => Err(rquickjs::Error::Unknown),
+                    })
+                    .unwrap()
                 });
                 assert_eq!(result, JSJavaProxy::Int(1));
             }
@@ -0,0 +1,45 @@
+use std::cell::RefCell;
+
+use rquickjs::{Context, Ctx, JsLifetime};
+use wasm_macros::wasm_export;
+
+use crate::js_to_java_proxy::JSJavaProxy;
+
+/// The most recent exception captured by a `FromError` impl for this `Context`,
+/// overwritten on every new failure. Stored as `Ctx` userdata, one slot per `Context`.
+#[derive(Default)]
+pub struct LastErrorSlot {
+    last: RefCell<Option<JSJavaProxy>>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for LastErrorSlot {
+    type Changed<'to> = LastErrorSlot;
+}
+
+impl LastErrorSlot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Records `proxy` as the last error for `ctx`'s `Context`, replacing whatever was
+/// recorded before. A missing `LastErrorSlot` is silently ignored, since every
+/// `Context` registers one in `create_context` - there's nothing a caller could do
+/// about it anyway.
+pub(crate) fn set_last_error<'js>(ctx: &Ctx<'js>, proxy: JSJavaProxy) {
+    if let Some(slot) = ctx.userdata::<LastErrorSlot>() {
+        slot.last.replace(Some(proxy));
+    }
+}
+
+/// Fetches and clears the last error recorded for `context`, so Java can recover the
+/// full diagnostics (class name, message, source-mapped stack, `cause` chain) after a
+/// call that could only report failure through a lossy sentinel (`-1`, `false`,
+/// `None`). Returns `JSJavaProxy::Null` if nothing has failed since the last take.
+#[wasm_export]
+pub fn take_last_error(context: &Context) -> JSJavaProxy {
+    context.with(|ctx| match ctx.userdata::<LastErrorSlot>() {
+        Some(slot) => slot.last.borrow_mut().take().unwrap_or(JSJavaProxy::Null),
+        None => JSJavaProxy::Null,
+    })
+}
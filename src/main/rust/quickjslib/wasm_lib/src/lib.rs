@@ -2,11 +2,24 @@ use std::mem;
 use std::slice;
 
 use wasm_macros::wasm_export;
+mod array_codec;
+mod arraybuffer;
+mod async_java_function;
+mod completable_future;
 mod context;
+mod from_error;
+mod handle_registry;
+mod into_wasm_result;
 mod java_log;
+mod java_ref;
 mod js_to_java_proxy;
+mod last_error;
+mod module_loader;
+mod native_array;
+mod native_object;
 mod quickjs_function;
 mod runtime;
+mod source_map;
 
 /// Give the host a way to free memory to prevent leaks
 #[no_mangle]
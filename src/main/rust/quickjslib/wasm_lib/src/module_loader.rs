@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use log::{debug, error};
+use rquickjs::loader::{Loader, Resolver};
+use rquickjs::{Ctx, Module, Runtime};
+use wasm_macros::wasm_export;
+
+use crate::js_to_java_proxy::JSJavaProxy;
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    /// Asks Java for the source text of the module named by the given UTF-8 bytes,
+    /// scoped to `context_ptr` (the same per-`Context` identifier every other host
+    /// callback in this crate is keyed by), mirroring the ptr+len round trip
+    /// `call_java_function` uses for everything else. Returns a packed
+    /// `(ptr << 32) | len` pointing at the UTF-8 source, or `0` if the host has no
+    /// module under that name for that context.
+    pub fn resolve_module(context_ptr: u64, module_name_ptr: *const u8, module_name_len: usize) -> u64;
+}
+
+thread_local! {
+    /// Source text already fetched from Java, keyed by `(context_ptr, resolved specifier)`.
+    /// The same module can be imported by more than one importer in a graph, so avoid
+    /// asking Java for it twice - but two live `Context`s must not share a cache entry,
+    /// since the same module name can resolve to different source per context.
+    static MODULE_CACHE: RefCell<HashMap<(u64, String), String>> = RefCell::new(HashMap::new());
+}
+
+/// Fetches `name`'s source text from Java via `resolve_module`, caching it per `ctx`'s `Context`.
+fn fetch_module_source<'js>(ctx: &Ctx<'js>, name: &str) -> rquickjs::Result<String> {
+    let context_ptr = ctx
+        .userdata::<crate::context::ContextPtr>()
+        .ok_or(rquickjs::Error::Unknown)?
+        .ptr;
+
+    let cache_key = (context_ptr, name.to_string());
+    if let Some(cached) = MODULE_CACHE.with(|cache| cache.borrow().get(&cache_key).cloned()) {
+        return Ok(cached);
+    }
+
+    let name_bytes = name.as_bytes();
+    let result = unsafe { resolve_module(context_ptr, name_bytes.as_ptr(), name_bytes.len()) };
+    if result == 0 {
+        error!("Java module resolver has no source for '{}'", name);
+        return Err(rquickjs::Error::Unknown);
+    }
+
+    let ptr = (result >> 32) as usize;
+    let len = (result & 0xFFFF_FFFF) as usize;
+    let bytes = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+    let source = String::from_utf8(bytes.to_vec());
+    crate::dealloc(ptr as *mut u8, len);
+
+    let source = match source {
+        Ok(source) => source,
+        Err(e) => {
+            error!("Java module resolver returned non-UTF8 source for '{}': {}", name, e);
+            return Err(rquickjs::Error::Unknown);
+        }
+    };
+
+    MODULE_CACHE.with(|cache| cache.borrow_mut().insert(cache_key, source.clone()));
+    Ok(source)
+}
+
+/// Resolver half of the Java-backed loader: specifiers are passed through
+/// unchanged, so Java owns the whole namespace (bare specifiers, relative paths,
+/// virtual bundler paths, ...) rather than having QuickJS rewrite them.
+struct JavaModuleResolver;
+
+impl Resolver for JavaModuleResolver {
+    fn resolve<'js>(&mut self, _ctx: &Ctx<'js>, _base: &str, name: &str) -> rquickjs::Result<String> {
+        Ok(name.to_string())
+    }
+}
+
+/// Loader half of the Java-backed loader: fetches source text for an
+/// already-resolved specifier and declares it as a module.
+struct JavaModuleLoader;
+
+impl Loader for JavaModuleLoader {
+    fn load<'js>(&mut self, ctx: &Ctx<'js>, name: &str) -> rquickjs::Result<Module<'js>> {
+        let source = fetch_module_source(ctx, name)?;
+        debug!("Loaded module '{}' ({} source bytes)", name, source.len());
+        Module::declare(ctx.clone(), name, source)
+    }
+}
+
+/// Registers the Java-backed resolver/loader pair on `runtime`, so an `import`
+/// anywhere in the module graph is served by `resolve_module` instead of failing
+/// with "module not found". Called once from `create_runtime`.
+pub fn install_module_loader(runtime: &Runtime) {
+    runtime.set_loader(JavaModuleResolver, JavaModuleLoader);
+}
+
+/// Evaluates `source` as the top-level ES module named `name`, pulling in any
+/// transitive imports through the Java-backed loader installed by
+/// `install_module_loader`. Returns the module's namespace object, converted the
+/// same way any other value crossing into Java is.
+#[wasm_export]
+pub fn eval_module(ctx: &Ctx<'_>, name: String, source: String) -> rquickjs::Result<JSJavaProxy> {
+    debug!("Evaluating module '{}'", name);
+
+    let module = Module::declare(ctx.clone(), name, source)?;
+    let (module, promise) = module.eval()?;
+
+    // Drain the job queue so a top-level `await` in this module (or one it imports)
+    // settles before the namespace is read back.
+    while ctx.execute_pending_job() {}
+    promise.finish::<()>()?;
+
+    JSJavaProxy::from_js(ctx, module.namespace()?.into_value())
+}
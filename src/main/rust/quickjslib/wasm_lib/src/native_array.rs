@@ -1,139 +1,262 @@
 use log::{debug, error};
-use rquickjs::{prelude::This, Array, Context, Function, Persistent};
+use rquickjs::{function::Args, prelude::This, Array, Context, Function, Persistent};
 use wasm_macros::wasm_export;
 
-use crate::{context::handle_error, js_to_java_proxy::JSJavaProxy};
+use crate::{
+    context::handle_error,
+    handle_registry::RegisteredHandle,
+    js_to_java_proxy::{register_handle, with_registered_handle, JSJavaProxy},
+};
 
 #[wasm_export]
-pub fn array_create(context: &Context) -> Box<Persistent<Array<'static>>> {
-    let result = context.with(|ctx| {
+pub fn array_create(context: &Context) -> u64 {
+    context.with(|ctx| {
         let js_array = rquickjs::Array::new(ctx.clone()).unwrap();
         let persistent = Persistent::save(&ctx, js_array);
-        persistent
-    });
-
-    let result = Box::new(result);
-
-    result
+        register_handle(&ctx, RegisteredHandle::Array(persistent)).unwrap()
+    })
 }
 
 #[wasm_export]
-pub fn array_close(_context: &Context, object: Box<Persistent<Array<'static>>>) -> bool {
-    drop(object);
-    true
+pub fn array_close(context: &Context, handle: u64) -> bool {
+    crate::handle_registry::free_native_handle(context, handle)
 }
 
 #[wasm_export]
-pub fn array_size(context: &Context, persistent_array: &Persistent<Array<'static>>) -> i32 {
-    let result = context.with(|ctx| match persistent_array.clone().restore(&ctx) {
-        Ok(v) => v.len() as i32,
-        Err(err) => {
+pub fn array_size(context: &Context, handle: u64) -> i32 {
+    let result = context.with(|ctx| {
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Array(array) => Ok(array.clone().restore(&ctx)?.len() as i32),
+            _ => Err(rquickjs::Error::Unknown),
+        })
+        .unwrap_or_else(|err| {
             error!("Failed to restore persitent array: {}", err);
             -1
-        }
+        })
     });
     debug!("Size of the native array {}", result);
-    result as i32
+    result
 }
 
 #[wasm_export]
-pub fn array_add(
-    context: &Context,
-    persistent_array: &Persistent<Array<'static>>,
-    index: i32,
-    value: JSJavaProxy,
-) -> bool {
+pub fn array_add(context: &Context, handle: u64, index: i32, value: JSJavaProxy) -> bool {
     let result = context.with(|ctx| {
-        let array = persistent_array.clone().restore(&ctx).unwrap();
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Array(array) => {
+                let array = array.clone().restore(&ctx)?;
+                splice_array(array, index, 0, vec![value])
+            }
+            _ => Err(rquickjs::Error::Unknown),
+        })
+    });
+
+    match result {
+        Ok(_) => true,
+        Err(err) => {
+            error!("Failed to add element at index {} to array: {}", index, err);
+            false
+        }
+    }
+}
 
-        match splice_array(array, index, 0, Some(value)) {
-            Ok(_) => true,
-            Err(err) => {
-                error!("Failed to add element at index {} to array: {}", index, err);
-                false
+/// Inserts `values` at `index` in a single round trip, instead of one `array_add`
+/// call per element.
+#[wasm_export]
+pub fn array_add_all(context: &Context, handle: u64, index: i32, values: Vec<JSJavaProxy>) -> bool {
+    let result = context.with(|ctx| {
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Array(array) => {
+                let array = array.clone().restore(&ctx)?;
+                splice_array(array, index, 0, values)
             }
+            _ => Err(rquickjs::Error::Unknown),
+        })
+    });
+
+    match result {
+        Ok(_) => true,
+        Err(err) => {
+            error!("Failed to add elements at index {} to array: {}", index, err);
+            false
         }
+    }
+}
+
+/// Overwrites `count` elements starting at `start` with `values` in a single round
+/// trip, instead of one `array_set` call per element.
+#[wasm_export]
+pub fn array_set_range(context: &Context, handle: u64, start: i32, values: Vec<JSJavaProxy>) -> bool {
+    let count = values.len() as i32;
+    let result = context.with(|ctx| {
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Array(array) => {
+                let array = array.clone().restore(&ctx)?;
+                splice_array(array, start, count, values)
+            }
+            _ => Err(rquickjs::Error::Unknown),
+        })
     });
 
-    result
+    match result {
+        Ok(_) => true,
+        Err(err) => {
+            error!(
+                "Failed to set range at index {} (count {}) in array: {}",
+                start, count, err
+            );
+            false
+        }
+    }
 }
 
+/// Reads `count` elements starting at `start` in a single round trip, instead of one
+/// `array_get` call per element.
 #[wasm_export]
-pub fn array_set(
-    context: &Context,
-    persistent_array: &Persistent<Array<'static>>,
-    index: i32,
-    value: JSJavaProxy,
-) -> bool {
+pub fn array_get_range(context: &Context, handle: u64, start: i32, count: i32) -> JSJavaProxy {
     let result = context.with(|ctx| {
-        let array = persistent_array.clone().restore(&ctx).unwrap();
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Array(array) => {
+                let array = array.clone().restore(&ctx)?;
 
-        match array.set(index as usize, value) {
-            Ok(_) => true,
-            Err(err) => {
-                error!("Failed to set element at index {} in array: {}", index, err);
-                false
+                let mut values = Vec::with_capacity(count.max(0) as usize);
+                for i in start..start + count {
+                    let value: JSJavaProxy = match array.get(i as usize) {
+                        Ok(v) => v,
+                        Err(err) => {
+                            error!("Failed to get element at index {} from array: {}", i, err);
+                            handle_error(err, ctx.clone())
+                        }
+                    };
+                    values.push(value);
+                }
+                Ok(JSJavaProxy::Array(values))
             }
-        }
+            _ => Err(rquickjs::Error::Unknown),
+        })
     });
 
-    result
+    match result {
+        Ok(v) => v,
+        Err(err) => {
+            error!("Failed to restore persistent array: {}", err);
+            JSJavaProxy::Array(Vec::new())
+        }
+    }
 }
 
+/// General batch mutation: deletes `delete_count` elements starting at `index` and
+/// inserts `values` in their place, in a single round trip. `array_add`/
+/// `array_add_all`/`array_remove` are all special cases of this.
 #[wasm_export]
-pub fn array_get(
-    context: &Context,
-    persistent_array: &Persistent<Array<'static>>,
-    index: i32,
-) -> JSJavaProxy {
+pub fn array_splice(context: &Context, handle: u64, index: i32, delete_count: i32, values: Vec<JSJavaProxy>) -> bool {
     let result = context.with(|ctx| {
-        let array = persistent_array.clone().restore(&ctx).unwrap();
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Array(array) => {
+                let array = array.clone().restore(&ctx)?;
+                splice_array(array, index, delete_count, values)
+            }
+            _ => Err(rquickjs::Error::Unknown),
+        })
+    });
+
+    match result {
+        Ok(_) => true,
+        Err(err) => {
+            error!(
+                "Failed to splice array at index {} (delete_count {}): {}",
+                index, delete_count, err
+            );
+            false
+        }
+    }
+}
 
-        let result: JSJavaProxy = match array.get(index as usize) {
-            Ok(v) => v,
-            Err(err) => {
-                error!("Failed to get element from array: {}", err);
-                handle_error(err, ctx)
+#[wasm_export]
+pub fn array_set(context: &Context, handle: u64, index: i32, value: JSJavaProxy) -> bool {
+    let result = context.with(|ctx| {
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Array(array) => {
+                let array = array.clone().restore(&ctx)?;
+                array.set(index as usize, value)
             }
-        };
-        result
+            _ => Err(rquickjs::Error::Unknown),
+        })
     });
 
-    result
+    match result {
+        Ok(_) => true,
+        Err(err) => {
+            error!("Failed to set element at index {} in array: {}", index, err);
+            false
+        }
+    }
 }
 
 #[wasm_export]
-pub fn array_remove(
-    context: &Context,
-    persistent_array: &Persistent<Array<'static>>,
-    index: i32,
-) -> bool {
+pub fn array_get(context: &Context, handle: u64, index: i32) -> JSJavaProxy {
     let result = context.with(|ctx| {
-        let array = persistent_array.clone().restore(&ctx).unwrap();
-
-        match splice_array(array, index, 1, None) {
-            Ok(_) => true,
-            Err(err) => {
-                error!(
-                    "Failed to remove element at index {} from array: {}",
-                    index, err
-                );
-                false
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Array(array) => {
+                let array = array.clone().restore(&ctx)?;
+                match array.get(index as usize) {
+                    Ok(v) => Ok(v),
+                    Err(err) => {
+                        error!("Failed to get element from array: {}", err);
+                        Ok(handle_error(err, ctx.clone()))
+                    }
+                }
             }
+            _ => Err(rquickjs::Error::Unknown),
+        })
+    });
+
+    match result {
+        Ok(v) => v,
+        Err(err) => {
+            error!("Failed to restore persistent array: {}", err);
+            JSJavaProxy::Null
         }
+    }
+}
+
+#[wasm_export]
+pub fn array_remove(context: &Context, handle: u64, index: i32) -> bool {
+    let result = context.with(|ctx| {
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Array(array) => {
+                let array = array.clone().restore(&ctx)?;
+                splice_array(array, index, 1, Vec::new())
+            }
+            _ => Err(rquickjs::Error::Unknown),
+        })
     });
 
-    result
+    match result {
+        Ok(_) => true,
+        Err(err) => {
+            error!(
+                "Failed to remove element at index {} from array: {}",
+                index, err
+            );
+            false
+        }
+    }
 }
 
 /// Helper function to splice an array, by calling the splice method on the array.
 ///
+/// Builds the `splice` call arguments manually instead of a fixed Rust tuple, since
+/// `values` is variadic - callers from a single element (`array_add`/`array_remove`)
+/// up to an entire batch (`array_splice`) all funnel through here, restoring the
+/// persistent handle and touching JS exactly once regardless of how many values are
+/// involved.
+///
 /// # Arguments
 ///
 /// * `array` - The array to splice
 /// * `index` - The index to start splicing from
 /// * `delete_count` - The number of elements to delete
-/// * `value` - The value to insert
+/// * `values` - The values to insert at `index`
 ///
 /// # Returns
 ///
@@ -142,17 +265,20 @@ fn splice_array<'js>(
     array: Array<'js>,
     index: i32,
     delete_count: i32,
-    value: Option<JSJavaProxy>,
+    values: Vec<JSJavaProxy>,
 ) -> Result<(), rquickjs::Error> {
+    let ctx = array.ctx().clone();
     let obj = rquickjs::Value::from(array).into_object().unwrap();
     let splice: Function = obj.get("splice")?;
-    match value {
-        Some(v) => {
-            let _s: rquickjs::Value = splice.call((This(obj), index, delete_count, v))?;
-        }
-        None => {
-            let _s: rquickjs::Value = splice.call((This(obj), index, delete_count))?;
-        }
-    };
+
+    let mut args = Args::new(ctx, 2 + values.len());
+    args.push_arg(This(obj))?;
+    args.push_arg(index)?;
+    args.push_arg(delete_count)?;
+    for value in values {
+        args.push_arg(value)?;
+    }
+
+    let _s: rquickjs::Value = splice.call_arg(args)?;
     Ok(())
 }
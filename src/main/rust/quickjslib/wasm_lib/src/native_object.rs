@@ -1,59 +1,56 @@
+use std::collections::HashMap;
+
 use log::{debug, error, info, warn};
-use rquickjs::{object::ObjectKeysIter, Atom, Context, Ctx, IntoAtom, Object, Persistent};
+use rquickjs::{object::ObjectKeysIter, Array, Atom, Context, Ctx, IntoAtom, Object, Persistent, Value};
 use wasm_macros::wasm_export;
 
-use crate::{context::handle_error, js_to_java_proxy::JSJavaProxy};
+use crate::{
+    context::handle_error,
+    handle_registry::RegisteredHandle,
+    js_to_java_proxy::{register_handle, with_registered_handle, JSJavaProxy},
+};
 
 #[wasm_export]
-pub fn object_create(context: &Context) -> Box<Persistent<Object<'static>>> {
-    let result = context.with(|ctx| {
+pub fn object_create(context: &Context) -> u64 {
+    context.with(|ctx| {
         let js_object = rquickjs::Object::new(ctx.clone()).unwrap();
         let persistent = Persistent::save(&ctx, js_object);
-        persistent
-    });
-
-    let result = Box::new(result);
-
-    result
+        register_handle(&ctx, RegisteredHandle::Object(persistent)).unwrap()
+    })
 }
 
 #[wasm_export]
-pub fn object_close(_context: &Context, object: Box<Persistent<Object<'static>>>) -> bool {
-    drop(object);
-    true
+pub fn object_close(context: &Context, handle: u64) -> bool {
+    crate::handle_registry::free_native_handle(context, handle)
 }
 
 #[wasm_export]
-pub fn object_size(context: &Context, persistent_object: &Persistent<Object<'static>>) -> i32 {
-    let result = context.with(|ctx| match persistent_object.clone().restore(&ctx) {
-        Ok(v) => v.len() as i32,
-        Err(err) => {
+pub fn object_size(context: &Context, handle: u64) -> i32 {
+    let result = context.with(|ctx| {
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Object(object) => Ok(object.clone().restore(&ctx)?.len() as i32),
+            _ => Err(rquickjs::Error::Unknown),
+        })
+        .unwrap_or_else(|err| {
             error!("Failed to restore persistent object: {}", err);
             -1
-        }
+        })
     });
     debug!("Size of the native object {}", result);
-    result as i32
+    result
 }
 
 #[wasm_export]
-pub fn object_contains_key(
-    context: &Context,
-    persistent_object: &Persistent<Object<'static>>,
-    key: JSJavaProxy,
-) -> bool {
-    let result = context.with(|ctx| match persistent_object.clone().restore(&ctx) {
-        Ok(v) => match v.contains_key(key) {
-            Ok(v) => v,
-            Err(err) => {
-                error!("Failed to check if key exists in object: {}", err);
-                false
-            }
-        },
-        Err(err) => {
-            error!("Failed to restore persistent object: {}", err);
+pub fn object_contains_key(context: &Context, handle: u64, key: JSJavaProxy) -> bool {
+    let result = context.with(|ctx| {
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Object(object) => object.clone().restore(&ctx)?.contains_key(key),
+            _ => Err(rquickjs::Error::Unknown),
+        })
+        .unwrap_or_else(|err| {
+            error!("Failed to check if key exists in object: {}", err);
             false
-        }
+        })
     });
     if result {
         debug!("Key exists in the native object");
@@ -64,12 +61,8 @@ pub fn object_contains_key(
 }
 
 #[wasm_export]
-pub fn object_get_value(
-    context: &Context,
-    persistent_object: &Persistent<Object<'static>>,
-    key: JSJavaProxy,
-) -> JSJavaProxy {
-    let result = context.with(|ctx| match get_value(&ctx, persistent_object, key) {
+pub fn object_get_value(context: &Context, handle: u64, key: JSJavaProxy) -> JSJavaProxy {
+    let result = context.with(|ctx| match get_value(&ctx, handle, key) {
         Ok(v) => v,
         Err(err) => {
             error!("Failed to get value from object: {}", err);
@@ -79,40 +72,37 @@ pub fn object_get_value(
     result
 }
 
-fn get_value<'js>(
-    ctx: &Ctx<'js>,
-    persistent_object: &Persistent<Object<'static>>,
-    key: JSJavaProxy,
-) -> rquickjs::Result<JSJavaProxy> {
-    let object = persistent_object.clone().restore(ctx)?;
-    let key: Atom<'js> = key.into_atom(ctx)?;
-    if object.contains_key(key.clone())? {
-        info!("Key {:?} exists in object", key.to_string()?);
-        object.get(key.clone())?
-    } else {
-        info!("Key {:?} does not exist in object", key.to_string()?);
-        Ok(JSJavaProxy::Null)
-    }
+fn get_value<'js>(ctx: &Ctx<'js>, handle: u64, key: JSJavaProxy) -> rquickjs::Result<JSJavaProxy> {
+    with_registered_handle(ctx, handle, |h| match h {
+        RegisteredHandle::Object(object) => {
+            let object = object.clone().restore(ctx)?;
+            let key: Atom<'js> = key.into_atom(ctx)?;
+            if object.contains_key(key.clone())? {
+                info!("Key {:?} exists in object", key.to_string()?);
+                object.get(key.clone())?
+            } else {
+                info!("Key {:?} does not exist in object", key.to_string()?);
+                Ok(JSJavaProxy::Null)
+            }
+        }
+        _ => Err(rquickjs::Error::Unknown),
+    })
 }
 
 #[wasm_export]
-pub fn object_remove_value(
-    context: &Context,
-    persistent_object: &Persistent<Object<'static>>,
-    key: JSJavaProxy,
-) -> bool {
-    let result = context.with(|ctx| match persistent_object.clone().restore(&ctx) {
-        Ok(v) => match v.remove(key) {
-            Ok(_) => true,
-            Err(err) => {
-                error!("Failed to remove value from object: {}", err);
-                false
+pub fn object_remove_value(context: &Context, handle: u64, key: JSJavaProxy) -> bool {
+    let result = context.with(|ctx| {
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Object(object) => {
+                object.clone().restore(&ctx)?.remove(key)?;
+                Ok(true)
             }
-        },
-        Err(err) => {
-            error!("Failed to restore persistent object: {}", err);
+            _ => Err(rquickjs::Error::Unknown),
+        })
+        .unwrap_or_else(|err| {
+            error!("Failed to remove value from object: {}", err);
             false
-        }
+        })
     });
     if result {
         debug!("Value removed from the native object");
@@ -123,24 +113,19 @@ pub fn object_remove_value(
 }
 
 #[wasm_export]
-pub fn object_set_value(
-    context: &Context,
-    persistent_object: &Persistent<Object<'static>>,
-    key: JSJavaProxy,
-    value: JSJavaProxy,
-) -> bool {
-    let result = context.with(|ctx| match persistent_object.clone().restore(&ctx) {
-        Ok(v) => match v.set(key, value) {
-            Ok(_) => true,
-            Err(err) => {
-                error!("Failed to set value in object: {}", err);
-                false
+pub fn object_set_value(context: &Context, handle: u64, key: JSJavaProxy, value: JSJavaProxy) -> bool {
+    let result = context.with(|ctx| {
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Object(object) => {
+                object.clone().restore(&ctx)?.set(key, value)?;
+                Ok(true)
             }
-        },
-        Err(err) => {
-            error!("Failed to restore persistent object: {}", err);
+            _ => Err(rquickjs::Error::Unknown),
+        })
+        .unwrap_or_else(|err| {
+            error!("Failed to set value in object: {}", err);
             false
-        }
+        })
     });
     if result {
         info!("Value set in the native object");
@@ -151,34 +136,124 @@ pub fn object_set_value(
 }
 
 #[wasm_export]
-pub fn object_key_set(
-    context: &Context,
-    persistent_object: &Persistent<Object<'static>>,
-) -> JSJavaProxy {
-    let result = context.with(|ctx| match persistent_object.clone().restore(&ctx) {
-        Ok(v) => {
-            let object_keys: ObjectKeysIter<'_, JSJavaProxy> = v.keys();
-
-            let mut keys = Vec::new();
-            for key in object_keys.into_iter() {
-                match key {
-                    Ok(key) => {
-                        keys.push(key);
-                    }
-                    Err(err) => {
-                        error!("Failed to get key from object: {}", err);
-                        keys.push(handle_error(err, ctx.clone()));
+pub fn object_key_set(context: &Context, handle: u64) -> JSJavaProxy {
+    let result = context.with(|ctx| {
+        with_registered_handle(&ctx, handle, |h| match h {
+            RegisteredHandle::Object(object) => {
+                let object = object.clone().restore(&ctx)?;
+                let object_keys: ObjectKeysIter<'_, JSJavaProxy> = object.keys();
+
+                let mut keys = Vec::new();
+                for key in object_keys.into_iter() {
+                    match key {
+                        Ok(key) => {
+                            keys.push(key);
+                        }
+                        Err(err) => {
+                            error!("Failed to get key from object: {}", err);
+                            keys.push(handle_error(err, ctx.clone()));
+                        }
                     }
                 }
+                info!("Keys: {:?}", keys);
+                Ok(JSJavaProxy::Array(keys))
             }
-            info!("Keys: {:?}", keys);
-            JSJavaProxy::Array(keys)
-        }
+            _ => Err(rquickjs::Error::Unknown),
+        })
+    });
+
+    match result {
+        Ok(v) => v,
         Err(err) => {
             error!("Failed to restore persistent object: {}", err);
-            handle_error(err, ctx)
+            context.with(|ctx| handle_error(err, ctx))
         }
-    });
+    }
+}
 
-    result
+/// Returns a stable identity for an object/array `value`, so a structured clone walk
+/// can recognize a value it has already visited. This has to come from the engine's
+/// own notion of object identity (the pointer QuickJS boxes the value around), not a
+/// property stamped onto the source: the source is a live value the caller still owns
+/// after cloning, so mutating it (and risking a thrown exception on a frozen/sealed
+/// source, or a spurious key showing up in `Object.keys()`/`JSON.stringify()`) is not
+/// an option.
+fn value_identity<'js>(value: &Value<'js>) -> u64 {
+    unsafe { value.as_js_value().u.ptr as u64 }
+}
+
+/// Recursively clones `value`, creating fresh `Object`/`Array` targets and copying
+/// primitive leaves directly. `seen` maps a source value's identity to its already
+/// created clone, so cyclic graphs and shared sub-objects keep their sharing shape.
+/// Functions throw when `strict` is set, otherwise are substituted with `null`.
+/// Every other object, class instances included, is cloned as a plain object from
+/// its own enumerable string keys; its prototype is not carried over.
+fn structured_clone_value<'js>(
+    ctx: &Ctx<'js>,
+    value: Value<'js>,
+    seen: &mut HashMap<u64, Value<'js>>,
+    strict: bool,
+) -> rquickjs::Result<Value<'js>> {
+    if value.is_array() {
+        let source = value.as_array().unwrap().clone();
+        let id = value_identity(&value);
+        if let Some(clone) = seen.get(&id) {
+            return Ok(clone.clone());
+        }
+
+        let target = Array::new(ctx.clone())?;
+        seen.insert(id, target.as_value().clone());
+        for i in 0..source.len() {
+            let element: Value = source.get(i)?;
+            let cloned = structured_clone_value(ctx, element, seen, strict)?;
+            target.set(i, cloned)?;
+        }
+        Ok(target.into_value())
+    } else if value.is_object() && !value.is_function() {
+        let source = value.as_object().unwrap().clone();
+        let id = value_identity(&value);
+        if let Some(clone) = seen.get(&id) {
+            return Ok(clone.clone());
+        }
+
+        let target = Object::new(ctx.clone())?;
+        seen.insert(id, target.as_value().clone());
+        let keys: ObjectKeysIter<'_, String> = source.keys();
+        for key in keys {
+            let key = key?;
+            let v: Value = source.get(&key)?;
+            let cloned = structured_clone_value(ctx, v, seen, strict)?;
+            target.set(key, cloned)?;
+        }
+        Ok(target.into_value())
+    } else if value.is_function() {
+        if strict {
+            Err(rquickjs::Error::Exception)
+        } else {
+            Ok(Value::new_null(ctx.clone()))
+        }
+    } else {
+        // Primitive leaf (number, string, bool, null, undefined): copy by value.
+        Ok(value)
+    }
+}
+
+/// Structured-clones the object graph behind `handle`, giving Java a fresh
+/// `NativeObject` handle for a detached snapshot it can hold across later mutations
+/// of the source. Fails (surfaced as a `JSJavaProxy::Exception` to Java) if cloning
+/// fails, e.g. a non-cloneable leaf is hit while `strict` is set.
+#[wasm_export]
+pub fn object_structured_clone(ctx: &Ctx<'_>, handle: u64, strict: bool) -> rquickjs::Result<JSJavaProxy> {
+    with_registered_handle(ctx, handle, |h| match h {
+        RegisteredHandle::Object(object) => {
+            let source = object.clone().restore(ctx)?;
+            let mut seen = HashMap::new();
+            let cloned = structured_clone_value(ctx, source.into_value(), &mut seen, strict)?;
+            let cloned_object = cloned.into_object().unwrap();
+            let cloned_persistent = Persistent::save(ctx, cloned_object);
+            let cloned_handle = register_handle(ctx, RegisteredHandle::Object(cloned_persistent))?;
+            Ok(JSJavaProxy::NativeObject(cloned_handle))
+        }
+        _ => Err(rquickjs::Error::Unknown),
+    })
 }
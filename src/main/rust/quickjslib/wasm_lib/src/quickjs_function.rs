@@ -4,28 +4,31 @@ use rquickjs::IntoJs;
 use rquickjs::Value;
 use rquickjs::{
     function::{IntoJsFunc, ParamRequirement},
-    Context, Ctx, Function, Persistent,
+    Context, Ctx, Function,
 };
 use wasm_macros::wasm_export;
 
-use crate::js_to_java_proxy::JSJavaProxy;
+use crate::{
+    handle_registry::RegisteredHandle,
+    js_to_java_proxy::{with_registered_handle, JSJavaProxy},
+};
 
 #[wasm_export]
-pub fn call_function<'js>(
-    ctx: &Ctx<'_>,
-    persistent_function: &Persistent<Function<'static>>,
-    args: JSJavaProxy,
-) -> rquickjs::Result<JSJavaProxy> {
-    let function = persistent_function.clone().restore(&ctx)?;
-    debug!("Calling function with args: {:?}", args);
-    function.call(args)?
+pub fn call_function<'js>(ctx: &Ctx<'_>, handle: u64, args: JSJavaProxy) -> rquickjs::Result<JSJavaProxy> {
+    with_registered_handle(ctx, handle, |h| match h {
+        RegisteredHandle::Function(function) => {
+            let function = function.clone().restore(ctx)?;
+            debug!("Calling function with args: {:?}", args);
+            function.call(args)?
+        }
+        _ => Err(rquickjs::Error::Unknown),
+    })
 }
 
 #[wasm_export]
-pub fn close_function(_context: &Context, object: Box<Persistent<Function<'static>>>) -> bool {
+pub fn close_function(context: &Context, handle: u64) -> bool {
     debug!("Closing js function wrapper");
-    drop(object);
-    true
+    crate::handle_registry::free_native_handle(context, handle)
 }
 
 #[link(wasm_import_module = "env")]
@@ -111,12 +114,13 @@ impl<'js, P> IntoJsFunc<'js, P> for JavaFunction {
         let arg = JSJavaProxy::Array(args);
         let result = (self.call)(arg);
 
-        // If the result is an exception, throw it
-        if let JSJavaProxy::Exception(message, _stacktrace) = &result {
-            let exception = rquickjs::Exception::from_message(params.ctx().clone(), &message)?;
-            Err(params.ctx().throw(exception.into_value()))
+        // If the result is an exception, throw it (as the matching JS error class)
+        let is_exception = matches!(result, JSJavaProxy::Exception { .. });
+        let value = result.into_js(params.ctx())?;
+        if is_exception {
+            Err(params.ctx().throw(value))
         } else {
-            result.into_js(params.ctx())
+            Ok(value)
         }
     }
 }
@@ -15,6 +15,8 @@ pub fn create_runtime() -> Box<Runtime> {
 
     runtime.set_interrupt_handler(Some(Box::new(interrrupt_handler)));
 
+    crate::module_loader::install_module_loader(&runtime);
+
     Box::new(runtime)
 }
 
@@ -0,0 +1,415 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use log::error;
+use rquickjs::{Context, Ctx, JsLifetime};
+use wasm_macros::wasm_export;
+
+/// One VLQ-decoded mapping segment for a single generated line: at generated column
+/// `gen_col`, the original location is `sources[source_idx]:orig_line:orig_col`.
+/// `source_idx`/`name_idx` are `None` for segments that only carry a generated
+/// column, mirroring the source-map spec's 1-, 4- and 5-field segment shapes.
+struct Segment {
+    gen_col: u32,
+    source_idx: Option<u32>,
+    orig_line: u32,
+    orig_col: u32,
+    name_idx: Option<u32>,
+}
+
+/// A decoded source map for one script, ready for `lookup` to binary search without
+/// re-parsing the VLQ `mappings` string on every stack frame.
+struct SourceMap {
+    sources: Vec<String>,
+    names: Vec<String>,
+    /// `lines[generated_line]` holds that line's segments, sorted by `gen_col`.
+    lines: Vec<Vec<Segment>>,
+}
+
+impl SourceMap {
+    /// Parses the standard `{version,sources,names,mappings}` JSON source map
+    /// format. Returns `None` if `bytes` isn't valid UTF-8/JSON or has no `mappings`.
+    fn parse(bytes: &[u8]) -> Option<SourceMap> {
+        let text = std::str::from_utf8(bytes).ok()?;
+        let value = json::parse(text)?;
+        let object = value.as_object()?;
+
+        let strings = |key: &str| -> Vec<String> {
+            object
+                .get(key)
+                .and_then(json::Value::as_array)
+                .map(|arr| arr.iter().filter_map(json::Value::as_str).map(str::to_string).collect())
+                .unwrap_or_default()
+        };
+
+        let sources = strings("sources");
+        let names = strings("names");
+        let mappings = object.get("mappings").and_then(json::Value::as_str)?;
+
+        Some(SourceMap {
+            sources,
+            names,
+            lines: decode_mappings(mappings),
+        })
+    }
+
+    /// Finds the nearest mapping segment at or before `(line, col)` (1-based, matching
+    /// a stack frame's own numbering) and returns the original `(source, line, col,
+    /// name)` it points at - the standard source-map "nearest preceding mapping" rule.
+    fn lookup(&self, line: u32, col: u32) -> Option<(&str, u32, u32, Option<&str>)> {
+        let segments = self.lines.get(line.checked_sub(1)? as usize)?;
+        if segments.is_empty() {
+            return None;
+        }
+
+        let idx = match segments.binary_search_by_key(&col.saturating_sub(1), |s| s.gen_col) {
+            Ok(idx) => idx,
+            Err(0) => return None,
+            Err(idx) => idx - 1,
+        };
+
+        let segment = &segments[idx];
+        let source = self.sources.get(segment.source_idx? as usize)?.as_str();
+        let name = segment.name_idx.and_then(|i| self.names.get(i as usize)).map(String::as_str);
+        Some((source, segment.orig_line + 1, segment.orig_col + 1, name))
+    }
+}
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Decodes one base64-VLQ value starting at `*pos`, advancing `*pos` past it.
+/// Each base64 digit contributes 5 value bits plus a continuation bit (0x20); the
+/// final digit's low bit is the sign, as specified by the source-map VLQ encoding.
+fn decode_vlq(bytes: &[u8], pos: &mut usize) -> Option<i64> {
+    let mut result: i64 = 0;
+    let mut shift = 0;
+    loop {
+        let digit = BASE64_ALPHABET.iter().position(|&b| b == *bytes.get(*pos)?)? as i64;
+        *pos += 1;
+        result += (digit & 0x1f) << shift;
+        shift += 5;
+        if digit & 0x20 == 0 {
+            break;
+        }
+    }
+    Some(if result & 1 != 0 { -(result >> 1) } else { result >> 1 })
+}
+
+/// Decodes the `mappings` string into a per-generated-line segment index. Segment
+/// fields are deltas against the previous segment (generated column resets every
+/// line, the rest accumulate across the whole map), per the source-map spec.
+fn decode_mappings(mappings: &str) -> Vec<Vec<Segment>> {
+    let (mut source_idx, mut orig_line, mut orig_col, mut name_idx): (i64, i64, i64, i64) =
+        (0, 0, 0, 0);
+
+    mappings
+        .split(';')
+        .map(|line| {
+            let mut gen_col: i64 = 0;
+            let mut segments: Vec<Segment> = line
+                .split(',')
+                .filter(|segment| !segment.is_empty())
+                .filter_map(|segment| {
+                    let bytes = segment.as_bytes();
+                    let mut pos = 0;
+                    let mut fields = Vec::with_capacity(5);
+                    while pos < bytes.len() {
+                        fields.push(decode_vlq(bytes, &mut pos)?);
+                    }
+
+                    gen_col += fields.first()?;
+                    if fields.len() < 4 {
+                        return Some(Segment {
+                            gen_col: gen_col as u32,
+                            source_idx: None,
+                            orig_line: 0,
+                            orig_col: 0,
+                            name_idx: None,
+                        });
+                    }
+
+                    source_idx += fields[1];
+                    orig_line += fields[2];
+                    orig_col += fields[3];
+                    let name = fields.get(4).map(|delta| {
+                        name_idx += delta;
+                        name_idx as u32
+                    });
+
+                    Some(Segment {
+                        gen_col: gen_col as u32,
+                        source_idx: Some(source_idx as u32),
+                        orig_line: orig_line as u32,
+                        orig_col: orig_col as u32,
+                        name_idx: name,
+                    })
+                })
+                .collect();
+            segments.sort_by_key(|s| s.gen_col);
+            segments
+        })
+        .collect()
+}
+
+/// Decoded source maps for the current `Context`, keyed by the script name they were
+/// registered under. Stored as `Ctx` userdata, one table per `Context`.
+#[derive(Default)]
+pub struct SourceMapRegistry {
+    maps: RefCell<HashMap<String, SourceMap>>,
+}
+
+unsafe impl<'js> JsLifetime<'js> for SourceMapRegistry {
+    type Changed<'to> = SourceMapRegistry;
+}
+
+impl SourceMapRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Registers a source map for `script_name`, so later stack traces whose frames
+/// reference that name get rewritten to point at the original source instead of the
+/// transpiled/bundled JS QuickJS actually ran.
+#[wasm_export]
+pub fn register_source_map(context: &Context, script_name: String, map_bytes: Vec<u8>) -> bool {
+    let map = match SourceMap::parse(&map_bytes) {
+        Some(map) => map,
+        None => {
+            error!("Failed to parse source map for {}", script_name);
+            return false;
+        }
+    };
+
+    context.with(|ctx| match ctx.userdata::<SourceMapRegistry>() {
+        Some(registry) => {
+            registry.maps.borrow_mut().insert(script_name, map);
+            true
+        }
+        None => {
+            error!("SourceMapRegistry userdata missing on context");
+            false
+        }
+    })
+}
+
+/// Rewrites each `at ... file:line:col` frame of `stacktrace` whose file has a
+/// registered source map, recovering the original source/line/col. Frames for an
+/// unregistered file, or that don't parse as a location, are passed through unchanged.
+pub(crate) fn rewrite_stacktrace(ctx: &Ctx<'_>, stacktrace: &str) -> String {
+    let registry = match ctx.userdata::<SourceMapRegistry>() {
+        Some(registry) => registry,
+        None => return stacktrace.to_string(),
+    };
+    let maps = registry.maps.borrow();
+    if maps.is_empty() {
+        return stacktrace.to_string();
+    }
+
+    stacktrace
+        .lines()
+        .map(|line| rewrite_frame(line, &maps).unwrap_or_else(|| line.to_string()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn rewrite_frame(line: &str, maps: &HashMap<String, SourceMap>) -> Option<String> {
+    let trimmed = line.trim_end();
+    let has_trailing_paren = trimmed.ends_with(')');
+    let body = if has_trailing_paren {
+        &trimmed[..trimmed.len() - 1]
+    } else {
+        trimmed
+    };
+
+    let mut fields = body.rsplitn(3, ':');
+    let col: u32 = fields.next()?.parse().ok()?;
+    let gen_line: u32 = fields.next()?.parse().ok()?;
+    let rest = fields.next()?;
+
+    let file_start = rest.rfind(|c: char| c == ' ' || c == '(').map(|i| i + 1).unwrap_or(0);
+    let (prefix, file) = rest.split_at(file_start);
+
+    let map = maps.get(file)?;
+    let (source, orig_line, orig_col, _name) = map.lookup(gen_line, col)?;
+
+    let mut rewritten = format!("{}{}:{}:{}", prefix, source, orig_line, orig_col);
+    if has_trailing_paren {
+        rewritten.push(')');
+    }
+    Some(rewritten)
+}
+
+/// Minimal recursive-descent JSON parser covering exactly the subset `SourceMap::parse`
+/// needs (objects, arrays, strings, plus numbers/booleans/null so fields such as
+/// `version` parse) - not a general-purpose JSON library, so reading
+/// four fields out of a source map doesn't pull in an external crate.
+mod json {
+    use std::collections::HashMap;
+
+    pub enum Value {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Value>),
+        Object(HashMap<String, Value>),
+    }
+
+    impl Value {
+        pub fn as_object(&self) -> Option<&HashMap<String, Value>> {
+            match self {
+                Value::Object(map) => Some(map),
+                _ => None,
+            }
+        }
+
+        pub fn as_array(&self) -> Option<&Vec<Value>> {
+            match self {
+                Value::Array(arr) => Some(arr),
+                _ => None,
+            }
+        }
+
+        pub fn as_str(&self) -> Option<&str> {
+            match self {
+                Value::String(s) => Some(s.as_str()),
+                _ => None,
+            }
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Value> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut pos = 0;
+        parse_value(&chars, &mut pos)
+    }
+
+    fn skip_ws(chars: &[char], pos: &mut usize) {
+        while matches!(chars.get(*pos), Some(c) if c.is_whitespace()) {
+            *pos += 1;
+        }
+    }
+
+    fn parse_value(chars: &[char], pos: &mut usize) -> Option<Value> {
+        skip_ws(chars, pos);
+        match *chars.get(*pos)? {
+            '{' => parse_object(chars, pos),
+            '[' => parse_array(chars, pos),
+            '"' => parse_string(chars, pos).map(Value::String),
+            't' => parse_literal(chars, pos, "true", Value::Bool(true)),
+            'f' => parse_literal(chars, pos, "false", Value::Bool(false)),
+            'n' => parse_literal(chars, pos, "null", Value::Null),
+            _ => parse_number(chars, pos),
+        }
+    }
+
+    fn parse_literal(chars: &[char], pos: &mut usize, lit: &str, value: Value) -> Option<Value> {
+        for c in lit.chars() {
+            if *chars.get(*pos)? != c {
+                return None;
+            }
+            *pos += 1;
+        }
+        Some(value)
+    }
+
+    fn parse_number(chars: &[char], pos: &mut usize) -> Option<Value> {
+        let start = *pos;
+        while matches!(chars.get(*pos), Some(c) if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            *pos += 1;
+        }
+        if *pos == start {
+            return None;
+        }
+        chars[start..*pos].iter().collect::<String>().parse::<f64>().ok().map(Value::Number)
+    }
+
+    fn parse_string(chars: &[char], pos: &mut usize) -> Option<String> {
+        if *chars.get(*pos)? != '"' {
+            return None;
+        }
+        *pos += 1;
+        let mut out = String::new();
+        loop {
+            let c = *chars.get(*pos)?;
+            *pos += 1;
+            match c {
+                '"' => return Some(out),
+                '\\' => {
+                    let escaped = *chars.get(*pos)?;
+                    *pos += 1;
+                    match escaped {
+                        'n' => out.push('\n'),
+                        't' => out.push('\t'),
+                        'r' => out.push('\r'),
+                        '"' => out.push('"'),
+                        '\\' => out.push('\\'),
+                        '/' => out.push('/'),
+                        'u' => {
+                            let code: String = chars.get(*pos..*pos + 4)?.iter().collect();
+                            *pos += 4;
+                            out.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                        }
+                        other => out.push(other),
+                    }
+                }
+                other => out.push(other),
+            }
+        }
+    }
+
+    fn parse_array(chars: &[char], pos: &mut usize) -> Option<Value> {
+        *pos += 1; // consume '['
+        let mut items = Vec::new();
+        skip_ws(chars, pos);
+        if *chars.get(*pos)? == ']' {
+            *pos += 1;
+            return Some(Value::Array(items));
+        }
+        loop {
+            items.push(parse_value(chars, pos)?);
+            skip_ws(chars, pos);
+            match *chars.get(*pos)? {
+                ',' => *pos += 1,
+                ']' => {
+                    *pos += 1;
+                    break;
+                }
+                _ => return None,
+            }
+        }
+        Some(Value::Array(items))
+    }
+
+    fn parse_object(chars: &[char], pos: &mut usize) -> Option<Value> {
+        *pos += 1; // consume '{'
+        let mut map = HashMap::new();
+        skip_ws(chars, pos);
+        if *chars.get(*pos)? == '}' {
+            *pos += 1;
+            return Some(Value::Object(map));
+        }
+        loop {
+            skip_ws(chars, pos);
+            let key = parse_string(chars, pos)?;
+            skip_ws(chars, pos);
+            if *chars.get(*pos)? != ':' {
+                return None;
+            }
+            *pos += 1;
+            map.insert(key, parse_value(chars, pos)?);
+            skip_ws(chars, pos);
+            match *chars.get(*pos)? {
+                ',' => *pos += 1,
+                '}' => {
+                    *pos += 1;
+                    break;
+                }
+                _ => return None,
+            }
+        }
+        Some(Value::Object(map))
+    }
+}